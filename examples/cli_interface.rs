@@ -2,8 +2,9 @@ use std::io;
 use std::io::BufRead;
 
 use xp_chess::board::piece::{Piece, PieceColor};
+use xp_chess::board::status::GameStatus;
 use xp_chess::board::Board;
-use xp_chess::consts::COLS;
+use xp_chess::consts::{COLS, ROWS};
 fn char_from_piece(piece: &Piece) -> char {
     let (ch, color) = match piece {
         Piece::Pawn(p) => ('P', p.color),
@@ -16,59 +17,54 @@ fn char_from_piece(piece: &Piece) -> char {
     };
 
     // Convert to lowercase if the piece is black
-    if color == PieceColor::White {
+    if color == PieceColor::Black {
         ch.to_ascii_lowercase()
     } else {
         ch
     }
 }
 fn print_board(board: &Board) {
-    for (i, piece) in board.board.iter().enumerate() {
-        let piece_char = char_from_piece(piece);
-        print!("{} ", piece_char);
-        if (i + 1) % COLS == 0 {
-            println!();
+    for row in 0..ROWS {
+        print!("{} ", ROWS - row);
+        for col in 0..COLS {
+            print!("{} ", char_from_piece(board.get_piece(row, col)));
         }
+        println!();
     }
+    println!("  a b c d e f g h");
 }
-fn get_row_col() -> (usize, usize) {
-    let stdin = io::stdin();
-    let line = stdin.lock().lines().next().unwrap().unwrap();
-    let mut parts = line.split_whitespace();
-    let row: usize = parts.next().unwrap().parse().unwrap();
-    let col: usize = parts.next().unwrap().parse().unwrap();
-    (row, col)
-}
-/// returns 0-indexed row and column for the piece to move and the destination
-fn get_rows_cols() -> (usize, usize, usize, usize) {
-    println!("Enter piece to move (row1 col1): ");
-    let (row1, col1) = get_row_col();
-    println!("Enter destination (row2 col2): ");
-    let (row2, col2) = get_row_col();
-    (row1 - 1, col1 - 1, row2 - 1, col2 - 1)
+/// prints every legal move of the side to move in SAN
+fn print_legal_moves(board: &Board) {
+    let mut board = board.clone();
+    let moves: Vec<String> = board
+        .get_all_legal_moves()
+        .into_iter()
+        .map(|(from, to)| board.move_to_san(from, to, None))
+        .collect();
+    println!("Legal moves: {}", moves.join(" "));
 }
 fn main() {
-    let mut board = Board::new();
-    for _ in 0..5 {
+    let mut board = Board::default();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
         print_board(&board);
-        let (row1, col1, row2, col2) = get_rows_cols();
-        let legal_moves = board.get_legal_moves((row1, col1));
-        println!("Legal moves:{:?}", legal_moves);
-        print_legal_moves(&legal_moves, &board);
-        board.move_piece((row1, col1), (row2, col2));
-    }
-}
-/// print the board like print_board but replace the parts attacked by the piece with 'X'
-fn print_legal_moves(legal_moves: &Vec<(usize, usize)>, board: &Board) {
-    for (i, piece) in board.board.iter().enumerate() {
-        let piece_char = if legal_moves.contains(&(i / COLS, i % COLS)) {
-            'X'
-        } else {
-            char_from_piece(piece)
+        if let GameStatus::Over(result) = board.game_status() {
+            println!("{}", result);
+            break;
+        }
+        print_legal_moves(&board);
+        println!("{:?} to move, enter a move in SAN (e.g. e4, Nf3, O-O):", board.turn_player);
+        let Some(Ok(line)) = lines.next() else {
+            break;
         };
-        print!("{} ", piece_char);
-        if i % COLS == COLS - 1 {
-            println!();
+        match board.parse_san(&line) {
+            Ok(mv) => {
+                let san = board.move_to_san(mv.from, mv.to, mv.promotion);
+                board.make_move(mv);
+                println!("played {}", san);
+            }
+            Err(e) => println!("{}", e),
         }
     }
 }
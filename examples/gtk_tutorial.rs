@@ -51,5 +51,5 @@ fn main() -> ExitCode {
     application.connect_activate(building_window);
     let exit_code = application.run();
     println!("closed the application");
-    return exit_code;
+    exit_code
 }
@@ -0,0 +1,75 @@
+//! Builds a Polyglot opening book from PGN files.
+//!
+//! usage: make-book [--depth <plies>] [--min-games <n>] <book.bin> <games.pgn>...
+//!
+//! Only the first `--depth` plies of each game (20 by default) go into the
+//! book, and only moves played in at least `--min-games` games (3 by default).
+use std::path::Path;
+use std::process::ExitCode;
+
+use xp_chess::book::BookBuilder;
+use xp_chess::pgn::parse_pgn;
+
+const USAGE: &str =
+    "usage: make-book [--depth <plies>] [--min-games <n>] <book.bin> <games.pgn>...";
+const DEFAULT_DEPTH: usize = 20;
+const DEFAULT_MIN_GAMES: u32 = 3;
+
+fn main() -> ExitCode {
+    let mut depth = DEFAULT_DEPTH;
+    let mut min_games = DEFAULT_MIN_GAMES;
+    let mut paths = vec![];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let valid = match arg.as_str() {
+            "--depth" => args.next().and_then(|n| n.parse().ok()).map(|n| depth = n),
+            "--min-games" => args
+                .next()
+                .and_then(|n| n.parse().ok())
+                .map(|n| min_games = n),
+            _ => {
+                paths.push(arg);
+                Some(())
+            }
+        };
+        if valid.is_none() {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    }
+    if paths.len() < 2 {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    }
+    let mut builder = BookBuilder::new(depth, min_games);
+    let mut games = 0;
+    for path in &paths[1..] {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let parsed = match parse_pgn(&text) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+        for game in &parsed {
+            if let Err(e) = builder.add_game(game) {
+                eprintln!("{}: skipping a game with a bad FEN tag: {}", path, e);
+                continue;
+            }
+            games += 1;
+        }
+    }
+    if let Err(e) = builder.write(Path::new(&paths[0])) {
+        eprintln!("{}: {}", paths[0], e);
+        return ExitCode::FAILURE;
+    }
+    println!("Read {} games into {}", games, paths[0]);
+    ExitCode::SUCCESS
+}
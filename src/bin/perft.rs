@@ -0,0 +1,62 @@
+//! Counts the legal move tree of a position, to check move generation against
+//! other programs.
+//!
+//! usage: perft [--divide] <depth> [fen]
+//!
+//! The FEN may be given as one argument or spread over several, and defaults to
+//! the starting position. With `--divide` the count below every move is printed
+//! too, in UCI notation.
+use std::process::ExitCode;
+use std::time::Instant;
+
+use xp_chess::board::fen::STARTING_FEN;
+use xp_chess::board::Board;
+
+const USAGE: &str = "usage: perft [--divide] <depth> [fen]";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let divide = match args.iter().position(|arg| arg == "--divide") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let Some(depth) = args.first().and_then(|depth| depth.parse::<u32>().ok()) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let fen = if args.len() > 1 {
+        args[1..].join(" ")
+    } else {
+        STARTING_FEN.to_string()
+    };
+    let board = match Board::from_fen(&fen) {
+        Ok(board) => board,
+        Err(e) => {
+            eprintln!("invalid FEN: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut total = 0;
+        for (mv, nodes) in board.divide(depth) {
+            println!("{}: {}", mv.to_uci(), nodes);
+            total += nodes;
+        }
+        println!();
+        total
+    } else {
+        board.perft(depth)
+    };
+    let elapsed = start.elapsed();
+    println!("Nodes searched: {}", nodes);
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+    ExitCode::SUCCESS
+}
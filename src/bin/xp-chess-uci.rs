@@ -0,0 +1,224 @@
+//! The built-in engine behind the Universal Chess Interface, so it can be loaded
+//! into chess GUIs and tournament managers. Commands come in on stdin, one per
+//! line, and replies go out on stdout.
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use xp_chess::board::piece::PieceColor;
+use xp_chess::board::Board;
+use xp_chess::engine::{self, Score, SearchLimits, SearchResult};
+use xp_chess::uci::{GoCommand, UciInfo};
+
+const ENGINE_NAME: &str = "xp-chess";
+const DEFAULT_MOVE_OVERHEAD_MS: u64 = 30;
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+/// how long to think when the GUI gives no limit for the side to move
+const FALLBACK_MOVE_TIME_MS: u64 = 1000;
+/// the share of the remaining time to spend on a move when the GUI gives no moves to go
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+struct Uci {
+    board: Board,
+    /// time held back from every move for the GUI and the pipe
+    move_overhead: Duration,
+    search: Option<RunningSearch>,
+}
+struct RunningSearch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+impl Uci {
+    fn new() -> Uci {
+        Uci {
+            board: Board::default(),
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            search: None,
+        }
+    }
+    /// handles one command. returns false once the engine should exit
+    fn handle(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                send(&format!("id name {}", ENGINE_NAME));
+                send("id author the xp-chess authors");
+                send(&format!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS
+                ));
+                send("uciok");
+            }
+            Some("isready") => send("readyok"),
+            Some("ucinewgame") => {
+                self.stop();
+                self.board = Board::default();
+            }
+            Some("position") => {
+                self.stop();
+                if let Err(e) = self.position(tokens.collect()) {
+                    send(&format!("info string {}", e));
+                }
+            }
+            Some("go") => {
+                self.stop();
+                self.go(GoCommand::parse(tokens.collect()));
+            }
+            Some("stop") => self.stop(),
+            Some("setoption") => self.set_option(tokens.collect()),
+            Some("quit") => {
+                self.stop();
+                return false;
+            }
+            // debug, ponderhit, register and anything unknown are ignored, as the
+            // protocol asks
+            _ => (),
+        }
+        true
+    }
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn position(&mut self, tokens: Vec<&str>) -> Result<(), String> {
+        let moves_at = tokens.iter().position(|&t| t == "moves");
+        let (setup, moves) = match moves_at {
+            Some(i) => (&tokens[..i], &tokens[i + 1..]),
+            None => (&tokens[..], &[][..]),
+        };
+        let mut board = match setup.split_first() {
+            Some((&"startpos", _)) => Board::default(),
+            Some((&"fen", fen)) => Board::from_fen(&fen.join(" ")).map_err(|e| e.to_string())?,
+            _ => return Err("expected startpos or fen".to_string()),
+        };
+        for uci in moves {
+            let mv = board
+                .parse_uci_move(uci)
+                .ok_or_else(|| format!("illegal move {}", uci))?;
+            board.make_move(mv);
+        }
+        self.board = board;
+        Ok(())
+    }
+    /// `setoption name <name> [value <value>]`
+    fn set_option(&mut self, tokens: Vec<&str>) {
+        let value_at = tokens.iter().position(|&t| t == "value");
+        let name = tokens[..value_at.unwrap_or(tokens.len())]
+            .iter()
+            .skip_while(|&&t| t == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = value_at.map(|i| tokens[i + 1..].join(" "));
+        match (name.to_lowercase().as_str(), value) {
+            ("move overhead", Some(value)) => match value.parse::<u64>() {
+                Ok(ms) => self.move_overhead = Duration::from_millis(ms.min(MAX_MOVE_OVERHEAD_MS)),
+                Err(_) => send(&format!("info string invalid Move Overhead {}", value)),
+            },
+            _ => send(&format!("info string unknown option {}", name)),
+        }
+    }
+    /// starts searching the current position on its own thread, which prints
+    /// `info` lines as depths complete and `bestmove` at the end
+    fn go(&mut self, go: GoCommand) {
+        let limits = SearchLimits {
+            depth: go.depth,
+            nodes: go.nodes,
+            move_time: self.move_time(&go),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let search_stop = stop.clone();
+        let board = self.board.clone();
+        let infinite = go.infinite;
+        let thread = thread::spawn(move || {
+            // a GUI waits forever for a bestmove that never comes, so a search that
+            // panics still answers with some legal move
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                engine::search(&board, &limits, &search_stop, |info| {
+                    send(&UciInfo::from(info).to_string());
+                })
+            }))
+            .unwrap_or_else(|_| {
+                send("info string the search failed");
+                SearchResult {
+                    best_move: board.legal_moves().into_iter().next(),
+                    score: Score::Centipawns(0),
+                    depth: 0,
+                    nodes: 0,
+                    pv: vec![],
+                }
+            });
+            // in infinite mode the GUI expects no bestmove before it says stop
+            while infinite && !search_stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            match result.best_move {
+                Some(best_move) => match result.pv.get(1) {
+                    Some(ponder) => send(&format!(
+                        "bestmove {} ponder {}",
+                        best_move.to_uci(),
+                        ponder.to_uci()
+                    )),
+                    None => send(&format!("bestmove {}", best_move.to_uci())),
+                },
+                None => send("bestmove 0000"),
+            }
+        });
+        self.search = Some(RunningSearch { stop, thread });
+    }
+    /// how long to think: `movetime` if given, otherwise a share of the clock, or a
+    /// fixed time when there is no clock and no depth or node limit either
+    fn move_time(&self, go: &GoCommand) -> Option<Duration> {
+        if go.infinite {
+            return None;
+        }
+        if let Some(move_time) = go.move_time {
+            return Some(
+                move_time
+                    .saturating_sub(self.move_overhead)
+                    .max(Duration::from_millis(1)),
+            );
+        }
+        let (time, increment) = match self.board.turn_player {
+            PieceColor::White => (go.white_time, go.white_increment.unwrap_or_default()),
+            PieceColor::Black => (go.black_time, go.black_increment.unwrap_or_default()),
+        };
+        let Some(time) = time else {
+            // a GUI that sends only the other side's clock, or nothing at all, still
+            // expects a bestmove without saying stop
+            if go.depth.is_none() && go.nodes.is_none() {
+                return Some(Duration::from_millis(FALLBACK_MOVE_TIME_MS));
+            }
+            return None;
+        };
+        let moves_to_go = go.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let budget = time / moves_to_go + increment * 3 / 4;
+        let ceiling = time.saturating_sub(self.move_overhead) / 2;
+        Some(budget.min(ceiling).max(Duration::from_millis(1)))
+    }
+    /// stops a running search and waits for it to print its bestmove
+    fn stop(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.stop.store(true, Ordering::Relaxed);
+            let _ = search.thread.join();
+        }
+    }
+}
+/// writes one line to the GUI, flushing so it never waits on a buffer
+fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{}", line);
+    let _ = stdout.flush();
+}
+fn main() {
+    let mut uci = Uci::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !uci.handle(line.trim()) {
+            return;
+        }
+    }
+    uci.stop();
+}
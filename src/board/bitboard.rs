@@ -0,0 +1,385 @@
+//! Sets of squares packed into a `u64` and the attack tables move generation is
+//! built on. Bit `row * COLS + col` stands for the square `(row, col)`, the same
+//! order as the board's own array, so bit 0 is a8 and bit 63 is h1.
+use std::sync::OnceLock;
+
+use super::piece::PieceColor;
+use crate::consts::{COLS, ROWS};
+
+pub type Bitboard = u64;
+
+const SQUARES: usize = ROWS * COLS;
+
+/// the squares of a light color, a8 being one of them
+pub const LIGHT_SQUARES: Bitboard = light_squares();
+
+/// the index of `(row, col)` in a bitboard
+pub fn square_index((row, col): (usize, usize)) -> usize {
+    row * COLS + col
+}
+/// the `(row, col)` of a bitboard index
+pub fn square_at(index: usize) -> (usize, usize) {
+    (index / COLS, index % COLS)
+}
+/// a bitboard holding only the given square
+pub fn bit(index: usize) -> Bitboard {
+    1 << index
+}
+/// the squares of a bitboard, lowest index first
+pub fn squares(bitboard: Bitboard) -> Squares {
+    Squares(bitboard)
+}
+
+/// Iterates over the squares set in a bitboard.
+pub struct Squares(Bitboard);
+impl Iterator for Squares {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
+const KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+    (1, -2),
+    (2, -1),
+    (2, 1),
+    (1, 2),
+];
+const KING_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+static KNIGHT_ATTACKS: [Bitboard; SQUARES] = leaper_attacks(&KNIGHT_OFFSETS);
+static KING_ATTACKS: [Bitboard; SQUARES] = leaper_attacks(&KING_OFFSETS);
+// white pawns move up the board, towards row 0
+static WHITE_PAWN_ATTACKS: [Bitboard; SQUARES] = leaper_attacks(&[(-1, -1), (-1, 1)]);
+static BLACK_PAWN_ATTACKS: [Bitboard; SQUARES] = leaper_attacks(&[(1, -1), (1, 1)]);
+
+pub fn knight_attacks(square: usize) -> Bitboard {
+    KNIGHT_ATTACKS[square]
+}
+pub fn king_attacks(square: usize) -> Bitboard {
+    KING_ATTACKS[square]
+}
+/// the squares a pawn of `color` on `square` captures on
+pub fn pawn_attacks(color: PieceColor, square: usize) -> Bitboard {
+    match color {
+        PieceColor::White => WHITE_PAWN_ATTACKS[square],
+        PieceColor::Black => BLACK_PAWN_ATTACKS[square],
+    }
+}
+/// the squares a bishop on `square` attacks, up to and including the first
+/// piece of either color in each direction
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let sliders = sliding_attacks();
+    sliders.table[sliders.bishops[square].index(occupied)]
+}
+/// the squares a rook on `square` attacks, up to and including the first
+/// piece of either color in each direction
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    let sliders = sliding_attacks();
+    sliders.table[sliders.rooks[square].index(occupied)]
+}
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+    bishop_attacks(square, occupied) | rook_attacks(square, occupied)
+}
+
+const fn on_board(row: i32, col: i32) -> bool {
+    0 <= row && row < ROWS as i32 && 0 <= col && col < COLS as i32
+}
+/// the squares one jump away from each square, for pieces that do not slide
+const fn leaper_attacks(offsets: &[(i32, i32)]) -> [Bitboard; SQUARES] {
+    let mut table = [0; SQUARES];
+    let mut square = 0;
+    while square < SQUARES {
+        let (row, col) = ((square / COLS) as i32, (square % COLS) as i32);
+        let mut i = 0;
+        while i < offsets.len() {
+            let (r, c) = (row + offsets[i].0, col + offsets[i].1);
+            if on_board(r, c) {
+                table[square] |= 1 << (r as usize * COLS + c as usize);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+const fn light_squares() -> Bitboard {
+    let mut squares = 0;
+    let mut square = 0;
+    while square < SQUARES {
+        if (square / COLS + square % COLS).is_multiple_of(2) {
+            squares |= 1 << square;
+        }
+        square += 1;
+    }
+    squares
+}
+
+// multipliers that hash every arrangement of blockers without a harmful collision,
+// found once by trying random sparse numbers and checking each against all of
+// the square's blocker sets
+const BISHOP_MAGICS: [u64; SQUARES] = [
+    0x10102002004a1420,
+    0x8020040400584008,
+    0x10510800811201c8,
+    0x5204042080000088,
+    0x2204106880000002,
+    0x1401042004000000,
+    0x0400880410042004,
+    0x0028208200a02020,
+    0x1500241990010e00,
+    0x8001200182020a40,
+    0x40004101030b0000,
+    0x8002041042000100,
+    0x4010011041020038,
+    0x0000010421044000,
+    0x1500210808020a00,
+    0x8000088400880520,
+    0x0405004010040100,
+    0x1005823210040108,
+    0x2708008102040011,
+    0x4048200404009100,
+    0x0018104101400024,
+    0x0003000601190101,
+    0x8004803108491000,
+    0x8014241200820800,
+    0x0006e080100c3040,
+    0x0501044a11041800,
+    0x9020300008004045,
+    0x0894080000220040,
+    0x1001010083104000,
+    0x5004030040900080,
+    0x000400422c012400,
+    0x0002128698404812,
+    0x1010108404900440,
+    0x0928021182084100,
+    0x2006080409020024,
+    0x1010202020180080,
+    0xa010008200202200,
+    0x2098015100019004,
+    0x0002041440810811,
+    0x802a02020000b098,
+    0x0009015090004060,
+    0x4000821082081001,
+    0x0100210040420800,
+    0x0800004010488a00,
+    0x2000081104004040,
+    0x4c8e029015000082,
+    0x0420340322224842,
+    0x1298260043400210,
+    0x0000822802400008,
+    0x00008a0101600000,
+    0x3040003412080021,
+    0x3040290220884800,
+    0x4a1500401041004a,
+    0x8010200282020781,
+    0x0020203142209091,
+    0x0070300600902110,
+    0x0040808800b62048,
+    0x0000810400c44420,
+    0x00080400440c0441,
+    0x8340080020840411,
+    0x0000000104208200,
+    0x0000800810d00080,
+    0x0400530411080200,
+    0x4040702400932244,
+];
+const ROOK_MAGICS: [u64; SQUARES] = [
+    0x1080004008801020,
+    0x0840092002c03000,
+    0x1900200010400900,
+    0x0880100008000480,
+    0x4200100420080200,
+    0x8100020100080400,
+    0x0200040110886200,
+    0x0200008040220411,
+    0x0404800084400220,
+    0x0000401000402000,
+    0x0086001081220440,
+    0x0408800800100280,
+    0x000a001201040820,
+    0x8848800200840080,
+    0x4001000100040200,
+    0x0442000102105084,
+    0x9080010020804100,
+    0x0040404000201009,
+    0x0000808010002009,
+    0x2200090021d00100,
+    0x0008008008040080,
+    0x0004004002010040,
+    0x0011040008015042,
+    0x00000a0001768104,
+    0x0000800080204009,
+    0x2010004140002001,
+    0x9800200280100080,
+    0x1000100080080080,
+    0x0442000a00049020,
+    0x2100040080020080,
+    0x0800120400900148,
+    0x0010040a00128541,
+    0x2800804000800030,
+    0x1010002000400041,
+    0x4000200011004100,
+    0x0610008410800800,
+    0x0400802402800800,
+    0xc100020080800400,
+    0x0002000802000401,
+    0x0182085882000401,
+    0x0220204000808000,
+    0x2860100040024022,
+    0x0001002004110040,
+    0x99101042000a0020,
+    0x0004080004008080,
+    0x0010040002008080,
+    0x2012004881020004,
+    0x8300842444820011,
+    0x0088403882010200,
+    0x0820400080210100,
+    0x0110910040a00300,
+    0x0801100280080480,
+    0x0242009008200600,
+    0x1002000489500200,
+    0x0040800200010080,
+    0x0091800041000080,
+    0x0000209300488001,
+    0x04c1002414824001,
+    0x020020000b001041,
+    0x7000100004200901,
+    0x8002002004100802,
+    0x30010002084c0007,
+    0x0888221800813004,
+    0x4000002840840112,
+];
+
+/// Where the attacks of a slider on one square start in the shared table, and
+/// the multiplier that hashes the pieces in its way to an offset from there.
+struct Magic {
+    /// the squares whose occupancy changes the attacks, the edges left out
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+impl Magic {
+    fn index(&self, occupied: Bitboard) -> usize {
+        self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+/// Magic bitboard lookups for bishops and rooks on every square.
+struct SlidingAttacks {
+    bishops: Vec<Magic>,
+    rooks: Vec<Magic>,
+    table: Vec<Bitboard>,
+}
+static SLIDING_ATTACKS: OnceLock<SlidingAttacks> = OnceLock::new();
+
+/// the lookup tables, filled on first use
+fn sliding_attacks() -> &'static SlidingAttacks {
+    SLIDING_ATTACKS.get_or_init(|| {
+        let mut table = vec![];
+        let bishops = (0..SQUARES)
+            .map(|square| {
+                let magic = BISHOP_MAGICS[square];
+                fill_magic(square, magic, &BISHOP_DIRECTIONS, &mut table)
+            })
+            .collect();
+        let rooks = (0..SQUARES)
+            .map(|square| fill_magic(square, ROOK_MAGICS[square], &ROOK_DIRECTIONS, &mut table))
+            .collect();
+        SlidingAttacks {
+            bishops,
+            rooks,
+            table,
+        }
+    })
+}
+/// stores the attacks for every arrangement of blockers around `square` at the
+/// slot `magic` hashes it to
+fn fill_magic(
+    square: usize,
+    magic: u64,
+    directions: &[(i32, i32)],
+    table: &mut Vec<Bitboard>,
+) -> Magic {
+    let mask = relevant_occupancy(square, directions);
+    let bits = mask.count_ones();
+    let offset = table.len();
+    table.resize(offset + (1 << bits), 0);
+    let magic = Magic {
+        mask,
+        magic,
+        shift: 64 - bits,
+        offset,
+    };
+    // every subset of the mask, walked with the carry-rippler trick
+    let mut subset: Bitboard = 0;
+    loop {
+        let attacks = slide(square, subset, directions);
+        let slot = &mut table[magic.index(subset)];
+        // a slider always attacks something, so a zero slot has not been written yet
+        debug_assert!(
+            *slot == 0 || *slot == attacks,
+            "magic collision on {}",
+            square
+        );
+        *slot = attacks;
+        subset = subset.wrapping_sub(mask) & mask;
+        if subset == 0 {
+            break;
+        }
+    }
+    magic
+}
+/// the squares between `square` and the edge in each direction. pieces on the
+/// edge never block anything further, so they are left out
+fn relevant_occupancy(square: usize, directions: &[(i32, i32)]) -> Bitboard {
+    let (row, col) = ((square / COLS) as i32, (square % COLS) as i32);
+    let mut mask = 0;
+    for &(dr, dc) in directions {
+        let (mut r, mut c) = (row + dr, col + dc);
+        while on_board(r + dr, c + dc) {
+            mask |= bit(r as usize * COLS + c as usize);
+            r += dr;
+            c += dc;
+        }
+    }
+    mask
+}
+/// walks each direction square by square until it leaves the board or hits a piece
+fn slide(square: usize, occupied: Bitboard, directions: &[(i32, i32)]) -> Bitboard {
+    let (row, col) = ((square / COLS) as i32, (square % COLS) as i32);
+    let mut attacks = 0;
+    for &(dr, dc) in directions {
+        let (mut r, mut c) = (row + dr, col + dc);
+        while on_board(r, c) {
+            let target = bit(r as usize * COLS + c as usize);
+            attacks |= target;
+            if occupied & target != 0 {
+                break;
+            }
+            r += dr;
+            c += dc;
+        }
+    }
+    attacks
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use super::piece::{Bishop, King, Knight, Pawn, Piece, PieceColor, Queen, Rook};
+use super::{parse_square, square_name, Board};
+use crate::consts::{COLS, ROWS};
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Everything that can be wrong with a FEN string handed to `Board::from_fen`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    /// the string has fewer than the four required fields
+    MissingField(&'static str),
+    /// more than the six standard fields were given
+    TooManyFields,
+    /// the placement field does not describe exactly eight ranks
+    WrongRankCount(usize),
+    /// a rank (1 to 8) does not add up to eight squares
+    WrongRankLength(usize),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    /// each side needs exactly one king
+    KingCount {
+        color: PieceColor,
+        count: usize,
+    },
+    /// a pawn stands on the first or eighth rank, given by its square
+    PawnOnBackRank(String),
+    /// the side that just moved left its king in check
+    OpponentInCheck,
+    /// the en passant square does not sit behind a pawn that just moved two
+    /// squares
+    EnPassantWithoutPawn(String),
+}
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing the {} field", field),
+            FenError::TooManyFields => write!(f, "too many fields, expected at most 6"),
+            FenError::WrongRankCount(count) => {
+                write!(
+                    f,
+                    "expected {} ranks in the board field, found {}",
+                    ROWS, count
+                )
+            }
+            FenError::WrongRankLength(rank) => {
+                write!(
+                    f,
+                    "rank {} does not describe exactly {} squares",
+                    rank, COLS
+                )
+            }
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::InvalidSideToMove(s) => {
+                write!(f, "side to move must be 'w' or 'b', found '{}'", s)
+            }
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+            FenError::KingCount { color, count } => {
+                write!(f, "expected one {:?} king, found {}", color, count)
+            }
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {}", square),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            FenError::EnPassantWithoutPawn(square) => write!(
+                f,
+                "en passant square {} is not behind a pawn that just moved two squares",
+                square
+            ),
+        }
+    }
+}
+impl std::error::Error for FenError {}
+
+impl Board {
+    /// builds a board from a FEN string. the two move counters may be left out,
+    /// in which case they default to `0 1`.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::MissingField("board"))?;
+        let side = fields
+            .next()
+            .ok_or(FenError::MissingField("side to move"))?;
+        let castling = fields.next().ok_or(FenError::MissingField("castling"))?;
+        let en_passant = fields.next().ok_or(FenError::MissingField("en passant"))?;
+        let halfmove_clock = match fields.next() {
+            Some(s) => s
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(s.to_string()))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.next() {
+            Some(s) => match s.parse() {
+                Ok(n) if n > 0 => n,
+                _ => return Err(FenError::InvalidFullmoveNumber(s.to_string())),
+            },
+            None => 1,
+        };
+        if fields.next().is_some() {
+            return Err(FenError::TooManyFields);
+        }
+
+        let mut board = Board {
+            halfmove_clock,
+            fullmove_number,
+            ..Board::empty()
+        };
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != ROWS {
+            return Err(FenError::WrongRankCount(ranks.len()));
+        }
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut col = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10) {
+                    col += skip as usize;
+                } else {
+                    let piece = piece_from_fen_char(c).ok_or(FenError::InvalidPiece(c))?;
+                    if col < COLS {
+                        board.set_piece(row, col, piece);
+                    }
+                    col += 1;
+                }
+            }
+            if col != COLS {
+                return Err(FenError::WrongRankLength(ROWS - row));
+            }
+        }
+        for color in [PieceColor::White, PieceColor::Black] {
+            let count = board.pieces[color.index()][super::KING].count_ones() as usize;
+            if count != 1 {
+                return Err(FenError::KingCount { color, count });
+            }
+        }
+        for row in [0, ROWS - 1] {
+            for col in 0..COLS {
+                if matches!(board.get_piece(row, col), Piece::Pawn(_)) {
+                    return Err(FenError::PawnOnBackRank(square_name((row, col))));
+                }
+            }
+        }
+
+        board.turn_player = match side {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => return Err(FenError::InvalidSideToMove(side.to_string())),
+        };
+        let waiting = board.turn_player.opposite();
+        if board.is_square_attacked(board.get_king_position(waiting), board.turn_player) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        if castling != "-" {
+            for c in castling.chars() {
+                let (color, rook_col) = match c {
+                    'K' => (PieceColor::White, COLS - 1),
+                    'Q' => (PieceColor::White, 0),
+                    'k' => (PieceColor::Black, COLS - 1),
+                    'q' => (PieceColor::Black, 0),
+                    _ => return Err(FenError::InvalidCastling(castling.to_string())),
+                };
+                let row = match color {
+                    PieceColor::White => ROWS - 1,
+                    PieceColor::Black => 0,
+                };
+                let king_ok = matches!(board.get_piece(row, 4), Piece::King(k) if k.color == color);
+                let rook_ok =
+                    matches!(board.get_piece(row, rook_col), Piece::Rook(r) if r.color == color);
+                if !king_ok || !rook_ok {
+                    return Err(FenError::InvalidCastling(castling.to_string()));
+                }
+                let rights = &mut board.castling;
+                match c {
+                    'K' => rights.white_kingside = true,
+                    'Q' => rights.white_queenside = true,
+                    'k' => rights.black_kingside = true,
+                    _ => rights.black_queenside = true,
+                }
+            }
+        }
+
+        board.en_passant_target = if en_passant == "-" {
+            None
+        } else {
+            // the target is behind a pawn that just moved two squares
+            let expected_row = match board.turn_player {
+                PieceColor::White => 2,
+                PieceColor::Black => ROWS - 3,
+            };
+            let (row, col) = match parse_square(en_passant) {
+                Some((row, col)) if row == expected_row => (row, col),
+                _ => return Err(FenError::InvalidEnPassant(en_passant.to_string())),
+            };
+            // the pawn stands one square past the target, coming from one before it
+            let (pawn_row, start_row) = match board.turn_player {
+                PieceColor::White => (row + 1, row - 1),
+                PieceColor::Black => (row - 1, row + 1),
+            };
+            let pawn_ok =
+                matches!(board.get_piece(pawn_row, col), Piece::Pawn(p) if p.color == waiting);
+            if !pawn_ok
+                || !board.get_piece(row, col).is_empty()
+                || !board.get_piece(start_row, col).is_empty()
+            {
+                return Err(FenError::EnPassantWithoutPawn(en_passant.to_string()));
+            }
+            Some((row, col))
+        };
+
+        board.hash = board.polyglot_key();
+        board.position_history.push(board.hash);
+        Ok(board)
+    }
+    /// writes the position as a FEN string
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for row in 0..ROWS {
+            let mut empty = 0;
+            for col in 0..COLS {
+                let piece = self.get_piece(row, col);
+                if piece.is_empty() {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                placement.push(fen_char_from_piece(piece));
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            if row != ROWS - 1 {
+                placement.push('/');
+            }
+        }
+        let side = match self.turn_player {
+            PieceColor::White => "w",
+            PieceColor::Black => "b",
+        };
+        let rights = self.castling_rights();
+        let mut castling = String::new();
+        for (has_right, c) in [
+            (rights.white_kingside, 'K'),
+            (rights.white_queenside, 'Q'),
+            (rights.black_kingside, 'k'),
+            (rights.black_queenside, 'q'),
+        ] {
+            if has_right {
+                castling.push(c);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant = self
+            .en_passant_target
+            .map(square_name)
+            .unwrap_or_else(|| "-".to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            placement, side, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
+/// FEN letters are uppercase for white, the opposite of `piece_from_char`
+fn piece_from_fen_char(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() {
+        PieceColor::White
+    } else {
+        PieceColor::Black
+    };
+    let piece = match c.to_ascii_lowercase() {
+        'p' => Piece::Pawn(Pawn { color }),
+        'n' => Piece::Knight(Knight { color }),
+        'b' => Piece::Bishop(Bishop { color }),
+        'r' => Piece::Rook(Rook { color }),
+        'q' => Piece::Queen(Queen { color }),
+        'k' => Piece::King(King { color }),
+        _ => return None,
+    };
+    Some(piece)
+}
+fn fen_char_from_piece(piece: &Piece) -> char {
+    let c = piece.to_char();
+    if c.is_ascii_lowercase() {
+        c.to_ascii_uppercase()
+    } else {
+        c.to_ascii_lowercase()
+    }
+}
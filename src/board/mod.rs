@@ -1,6 +1,22 @@
+pub mod bitboard;
+pub mod fen;
+pub mod moves;
+pub mod perft;
 pub mod piece;
+pub mod san;
+pub mod status;
+#[cfg(test)]
+mod tests;
+pub mod zobrist;
 use crate::consts::{COLS, ROWS};
-use piece::{piece_from_char, Empty, Piece, PieceColor, Queen};
+use bitboard::{
+    bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks,
+    square_at, square_index, squares, Bitboard, LIGHT_SQUARES,
+};
+use moves::{IrreversibleState, Move};
+use piece::{piece_from_char, Empty, Piece, PieceColor, PromotionPiece};
+use status::{GameEndReason, GameResult, GameStatus};
+use zobrist::{castling_key, piece_key, WHITE_TO_MOVE_KEY};
 const STARTING_BOARD_STR: [[char; COLS]; ROWS] = [
     ['R', 'N', 'B', 'Q', 'K', 'B', 'N', 'R'],
     ['P', 'P', 'P', 'P', 'P', 'P', 'P', 'P'],
@@ -11,155 +27,666 @@ const STARTING_BOARD_STR: [[char; COLS]; ROWS] = [
     ['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
     ['r', 'n', 'b', 'q', 'k', 'b', 'n', 'r'],
 ];
+// indices of each kind of piece in `Board::pieces`, see `Piece::kind_index`
+const PAWN: usize = 0;
+const KNIGHT: usize = 1;
+const BISHOP: usize = 2;
+const ROOK: usize = 3;
+const QUEEN: usize = 4;
+const KING: usize = 5;
 /// Represents a chess board with game state.
-#[derive(Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Board {
+    /// the piece on every square, kept in step with `pieces` so `get_piece` can
+    /// hand out references
     board: [Piece; ROWS * COLS],
+    /// the squares of every piece, by color and then by kind
+    pieces: [[Bitboard; 6]; 2],
+    /// the squares of all pieces of each color
+    occupied: [Bitboard; 2],
     pub turn_player: PieceColor,
+    castling: CastlingRights,
+    /// square skipped over by a pawn's double push on the previous ply
+    en_passant_target: Option<(usize, usize)>,
+    /// plies since the last capture or pawn move, for the fifty-move rule
+    halfmove_clock: u32,
+    /// starts at 1 and goes up after every black move
+    fullmove_number: u32,
+    /// the Zobrist key of the position, updated along with every change to it
+    hash: u64,
+    /// keys of every position reached so far, for threefold repetition
+    position_history: Vec<u64>,
+    /// moves played so far, oldest first
+    history: Vec<Move>,
+    /// what each move in `history` overwrote, so it can be undone
+    undo_states: Vec<IrreversibleState>,
 }
 impl Board {
+    /// a board without pieces or castling rights, white to move
+    fn empty() -> Board {
+        Board {
+            board: std::array::from_fn(|_| Piece::Empty(Empty {})),
+            pieces: [[0; 6]; 2],
+            occupied: [0; 2],
+            turn_player: PieceColor::White,
+            castling: CastlingRights::NONE,
+            en_passant_target: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: WHITE_TO_MOVE_KEY,
+            position_history: vec![],
+            history: vec![],
+            undo_states: vec![],
+        }
+    }
     pub fn get_piece(&self, row: usize, col: usize) -> &Piece {
         &self.board[row * COLS + col]
     }
+    /// puts a piece on a square, replacing whatever stood there. castling rights
+    /// are left alone
     pub fn set_piece(&mut self, row: usize, col: usize, piece: Piece) {
-        self.board[row * COLS + col] = piece;
+        self.put(square_index((row, col)), piece);
+    }
+    fn put(&mut self, square: usize, piece: Piece) {
+        self.remove(square);
+        if let (Some(color), Some(kind)) = (piece.color(), piece.kind_index()) {
+            self.pieces[color.index()][kind] |= bit(square);
+            self.occupied[color.index()] |= bit(square);
+        }
+        self.hash ^= piece_key(&piece, square);
+        self.board[square] = piece;
+    }
+    fn remove(&mut self, square: usize) -> Piece {
+        let piece = std::mem::replace(&mut self.board[square], Piece::Empty(Empty {}));
+        if let (Some(color), Some(kind)) = (piece.color(), piece.kind_index()) {
+            self.pieces[color.index()][kind] &= !bit(square);
+            self.occupied[color.index()] &= !bit(square);
+        }
+        self.hash ^= piece_key(&piece, square);
+        piece
+    }
+    /// the squares of every piece on the board
+    fn all_pieces(&self) -> Bitboard {
+        self.occupied[0] | self.occupied[1]
+    }
+    /// the en passant target as a bitboard, empty when there is none
+    fn en_passant_bit(&self) -> Bitboard {
+        self.en_passant_target
+            .map_or(0, |target| bit(square_index(target)))
+    }
+    /// the square a pawn can capture en passant onto this ply, if any
+    pub fn en_passant_target(&self) -> Option<(usize, usize)> {
+        self.en_passant_target
     }
+    /// plays `from` -> `to` if it is legal. a pawn reaching the last rank becomes
+    /// `promotion`, or a queen when no piece is given.
     pub fn try_move_piece(
         &mut self,
         from: (usize, usize),
         to: (usize, usize),
-    ) -> Result<MoveType, &str> {
-        let legal_moves = self.get_legal_moves(from);
-        if legal_moves.contains(&to) {
-            self.move_piece(from, to);
-            // handle promotion
-            if let Piece::Pawn(p) = self.get_piece(to.0, to.1) {
-                if (p.color == PieceColor::White && to.0 == 0)
-                    || (p.color == PieceColor::Black && to.0 == ROWS - 1)
-                {
-                    let cur_color = p.color;
-                    self.set_piece(to.0, to.1, Piece::Queen(Queen { color: p.color }));
-                    return Ok(MoveType::Promotion(Piece::Queen(Queen {
-                        color: cur_color,
-                    })));
-                }
+        promotion: Option<PromotionPiece>,
+    ) -> Result<Move, &str> {
+        if !self.get_legal_moves(from).contains(&to) {
+            return Err("Invalid move");
+        }
+        let mv = self.build_move(from, to, promotion);
+        self.make_move(mv.clone());
+        Ok(mv)
+    }
+    /// describes moving the piece on `from` to `to` in this position, without
+    /// checking that it is legal
+    pub fn build_move(
+        &self,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: Option<PromotionPiece>,
+    ) -> Move {
+        let piece = self.get_piece(from.0, from.1).clone();
+        let target = self.get_piece(to.0, to.1);
+        let mut captured = (!target.is_empty()).then(|| target.clone());
+        let mut promotion = promotion;
+        let kind = if self.is_castling_move(from, to) {
+            MoveType::Castle
+        } else if self.is_en_passant_move(from, to) {
+            captured = Some(self.get_piece(from.0, to.1).clone());
+            MoveType::EnPassant
+        } else if self.is_promotion_move(from, to) {
+            let chosen = promotion.unwrap_or(PromotionPiece::Queen);
+            promotion = Some(chosen);
+            MoveType::Promotion(chosen.to_piece(self.turn_player))
+        } else if captured.is_some() {
+            MoveType::Capture
+        } else {
+            MoveType::Move
+        };
+        if !matches!(kind, MoveType::Promotion(_)) {
+            promotion = None;
+        }
+        Move {
+            from,
+            to,
+            piece,
+            captured,
+            promotion,
+            kind,
+        }
+    }
+    /// plays a move and records it so it can be undone. the move has to be
+    /// legal, e.g. one from `legal_moves` or `build_move` on a legal square.
+    pub fn make_move(&mut self, mv: Move) {
+        let state = self.apply_move(&mv);
+        self.position_history.push(self.hash);
+        self.history.push(mv);
+        self.undo_states.push(state);
+    }
+    /// takes back the last move, restoring castling rights, the en passant
+    /// square and both clocks. returns the move, or `None` at the start of the game.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let mv = self.history.pop()?;
+        let state = self.undo_states.pop()?;
+        self.position_history.pop();
+        self.unapply_move(&mv, state);
+        Some(mv)
+    }
+    /// the moves played on this board so far, oldest first
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+    pub fn last_move(&self) -> Option<&Move> {
+        self.history.last()
+    }
+    /// a copy of the position alone, without the move and repetition history that
+    /// moves played on it only need for undo and draw detection
+    fn position_copy(&self) -> Board {
+        Board {
+            board: self.board.clone(),
+            pieces: self.pieces,
+            occupied: self.occupied,
+            turn_player: self.turn_player,
+            castling: self.castling,
+            en_passant_target: self.en_passant_target,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+            position_history: vec![],
+            history: vec![],
+            undo_states: vec![],
+        }
+    }
+    fn apply_move(&mut self, mv: &Move) -> IrreversibleState {
+        let state = IrreversibleState {
+            castling: self.castling,
+            en_passant_target: self.en_passant_target,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+        };
+        // castling rights and the en passant file are hashed out here and back
+        // in once the move is done
+        self.hash ^= castling_key(self.castling) ^ self.en_passant_key();
+        let piece = self.remove(square_index(mv.from));
+        let is_pawn = matches!(piece, Piece::Pawn(_));
+        match &mv.kind {
+            MoveType::Castle => {
+                let (rook_from, rook_to) = castling_rook_move(mv.from, mv.to);
+                let rook = self.remove(square_index(rook_from));
+                self.put(square_index(rook_to), rook);
+            }
+            // the captured pawn sits beside the moving pawn, not on the target square
+            MoveType::EnPassant => {
+                self.remove(square_index((mv.from.0, mv.to.1)));
             }
-            return Ok(MoveType::Capture);
+            _ => (),
         }
-        Err("Invalid move")
+        match &mv.kind {
+            MoveType::Promotion(promoted) => self.put(square_index(mv.to), promoted.clone()),
+            _ => self.put(square_index(mv.to), piece),
+        }
+        self.halfmove_clock = if is_pawn || mv.captured.is_some() {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        self.en_passant_target = (is_pawn && mv.from.0.abs_diff(mv.to.0) == 2)
+            .then_some(((mv.from.0 + mv.to.0) / 2, mv.from.1));
+        // moving a king or rook, or taking a rook, ends castling with it for good
+        self.castling.remove_for_square(mv.from);
+        self.castling.remove_for_square(mv.to);
+        if self.turn_player == PieceColor::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn_player = self.turn_player.opposite();
+        self.hash ^= castling_key(self.castling) ^ self.en_passant_key() ^ WHITE_TO_MOVE_KEY;
+        state
     }
-    fn move_piece(&mut self, from: (usize, usize), to: (usize, usize)) {
-        let (from_row, from_col) = from;
-        let (to_row, to_col) = to;
-        self.board
-            .swap(from_row * COLS + from_col, to_row * COLS + to_col);
-        self.set_piece(from_row, from_col, Piece::Empty(Empty {}));
+    fn unapply_move(&mut self, mv: &Move, state: IrreversibleState) {
+        self.remove(square_index(mv.to));
+        self.put(square_index(mv.from), mv.piece.clone());
+        match mv.kind {
+            MoveType::Castle => {
+                let (rook_from, rook_to) = castling_rook_move(mv.from, mv.to);
+                let rook = self.remove(square_index(rook_to));
+                self.put(square_index(rook_from), rook);
+            }
+            MoveType::EnPassant => {
+                if let Some(captured) = &mv.captured {
+                    self.put(square_index((mv.from.0, mv.to.1)), captured.clone());
+                }
+            }
+            _ => {
+                if let Some(captured) = &mv.captured {
+                    self.put(square_index(mv.to), captured.clone());
+                }
+            }
+        }
         self.turn_player = self.turn_player.opposite();
-        self.board[to_row * COLS + to_col].become_moved();
+        self.castling = state.castling;
+        self.en_passant_target = state.en_passant_target;
+        self.halfmove_clock = state.halfmove_clock;
+        self.fullmove_number = state.fullmove_number;
+        self.hash = state.hash;
+    }
+    /// returns true if moving the piece on `from` to `to` would promote a pawn,
+    /// so callers know to ask which piece it should become
+    pub fn is_promotion_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        match self.get_piece(from.0, from.1) {
+            Piece::Pawn(p) => to.0 == promotion_row(p.color),
+            _ => false,
+        }
     }
     /// returns a list of 0-indexed legal moves for a piece at a given position
     pub fn get_legal_moves(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
-        let mut moves = self.get_valid_moves_to_consider(from);
-        self.filter_legal_moves_check(from, &mut moves)
+        squares(self.legal_targets(square_index(from)))
+            .map(square_at)
+            .collect()
     }
-    fn get_valid_moves_to_consider(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
-        let (from_row, from_col) = from;
-        let piece = self.get_piece(from_row, from_col);
-        if piece.color() != Some(self.turn_player) {
-            return vec![];
+    /// every legal move for the side to move, with one move per promotion piece
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        for (from, to) in self.get_all_legal_moves() {
+            if self.is_promotion_move(from, to) {
+                for promotion in PromotionPiece::ALL {
+                    moves.push(self.build_move(from, to, Some(promotion)));
+                }
+            } else {
+                moves.push(self.build_move(from, to, None));
+            }
         }
-        match piece {
-            Piece::Empty(_) => vec![],
-            Piece::Pawn(p) => p.moves_to_consider((from_row, from_col), self),
-            Piece::Knight(k) => k.moves_to_consider((from_row, from_col), self),
-            Piece::Bishop(b) => b.moves_to_consider((from_row, from_col), self),
-            Piece::Rook(r) => r.moves_to_consider((from_row, from_col), self),
-            Piece::Queen(q) => q.moves_to_consider((from_row, from_col), self),
-            Piece::King(k) => {
-                // can't move to a square with a friendly piece
-                // can't castle if it has moved
-                // can't castle if the rook has moved
-                // can't castle if there are pieces between the king and rook
-                // can't castle if the king is in check
-                // can't castle if the king moves through check
-                k.moves_to_consider((from_row, from_col), self)
+        moves
+    }
+    /// every legal `(from, to)` pair for the side to move
+    pub fn get_all_legal_moves(&self) -> Vec<((usize, usize), (usize, usize))> {
+        let mut moves = vec![];
+        for from in squares(self.occupied[self.turn_player.index()]) {
+            for to in squares(self.legal_targets(from)) {
+                moves.push((square_at(from), square_at(to)));
             }
         }
+        moves
     }
-    fn filter_legal_moves_check(
-        &self,
-        from: (usize, usize),
-        moves: &mut Vec<(usize, usize)>,
-    ) -> Vec<(usize, usize)> {
-        let res = moves.iter().filter(|&to| {
-            let mut board = self.clone();
-            let cur_player = board.turn_player;
-            board.move_piece(from, *to);
-            !board.king_still_in_check(cur_player)
-        });
-        println!("Filtered moves: {:?}", moves);
-        res.cloned().collect()
+    /// the squares the piece on `from` may legally move to
+    fn legal_targets(&self, from: usize) -> Bitboard {
+        let mut targets = self.pseudo_legal_targets(from);
+        for to in squares(targets) {
+            if !self.keeps_king_safe(from, to) {
+                targets &= !bit(to);
+            }
+        }
+        targets
+    }
+    /// the squares the piece on `from` could move to if its own king's safety
+    /// did not matter. empty unless the piece belongs to the side to move
+    fn pseudo_legal_targets(&self, from: usize) -> Bitboard {
+        let us = self.turn_player;
+        let own = self.occupied[us.index()];
+        let all = self.all_pieces();
+        let piece = &self.board[from];
+        if piece.color() != Some(us) {
+            return 0;
+        }
+        match piece {
+            Piece::Empty(_) => 0,
+            Piece::Pawn(_) => self.pawn_targets(from),
+            Piece::Knight(_) => knight_attacks(from) & !own,
+            Piece::Bishop(_) => bishop_attacks(from, all) & !own,
+            Piece::Rook(_) => rook_attacks(from, all) & !own,
+            Piece::Queen(_) => queen_attacks(from, all) & !own,
+            Piece::King(_) => (king_attacks(from) & !own) | self.castling_targets(from),
+        }
+    }
+    fn pawn_targets(&self, from: usize) -> Bitboard {
+        let us = self.turn_player;
+        let empty = !self.all_pieces();
+        let (start_row, forward): (usize, fn(Bitboard) -> Bitboard) = match us {
+            PieceColor::White => (ROWS - 2, |b| b >> COLS),
+            PieceColor::Black => (1, |b| b << COLS),
+        };
+        let single = forward(bit(from)) & empty;
+        let mut targets = single;
+        if square_at(from).0 == start_row {
+            targets |= forward(single) & empty;
+        }
+        let enemies = self.occupied[us.opposite().index()] | self.en_passant_bit();
+        targets | (pawn_attacks(us, from) & enemies)
+    }
+    /// where the king on `from` may castle to: the squares between king and rook
+    /// must be empty, and the king may not start on, pass through or land on an
+    /// attacked square. the b-file square only has to be empty, the king never
+    /// crosses it
+    fn castling_targets(&self, from: usize) -> Bitboard {
+        let us = self.turn_player;
+        let them = us.opposite();
+        let row = home_row(us);
+        let (kingside, queenside) = match us {
+            PieceColor::White => (self.castling.white_kingside, self.castling.white_queenside),
+            PieceColor::Black => (self.castling.black_kingside, self.castling.black_queenside),
+        };
+        if from != square_index((row, 4))
+            || !(kingside || queenside)
+            || self.is_square_attacked((row, 4), them)
+        {
+            return 0;
+        }
+        let all = self.all_pieces();
+        let rooks = self.pieces[us.index()][ROOK];
+        let can_castle = |rook_col: usize, between: &[usize], king_path: [usize; 2]| {
+            rooks & bit(square_index((row, rook_col))) != 0
+                && between
+                    .iter()
+                    .all(|&col| all & bit(square_index((row, col))) == 0)
+                && king_path
+                    .iter()
+                    .all(|&col| !self.is_square_attacked((row, col), them))
+        };
+        let mut targets = 0;
+        if kingside && can_castle(COLS - 1, &[5, 6], [5, 6]) {
+            targets |= bit(square_index((row, 6)));
+        }
+        if queenside && can_castle(0, &[1, 2, 3], [3, 2]) {
+            targets |= bit(square_index((row, 2)));
+        }
+        targets
+    }
+    /// returns true if moving the piece on `from` to `to` leaves its own king out
+    /// of check. works on the bitboards the move would leave behind, without
+    /// playing it
+    fn keeps_king_safe(&self, from: usize, to: usize) -> bool {
+        let us = self.turn_player;
+        let is_king = self.pieces[us.index()][KING] & bit(from) != 0;
+        let mut captured = bit(to);
+        if self.pieces[us.index()][PAWN] & bit(from) != 0
+            && self.en_passant_bit() == bit(to)
+            && square_at(from).1 != square_at(to).1
+        {
+            captured = bit(square_index((square_at(from).0, square_at(to).1)));
+        }
+        let occupied = (self.all_pieces() & !bit(from) & !captured) | bit(to);
+        let king = if is_king { to } else { self.king_square(us) };
+        !self.is_attacked_after(king, us.opposite(), occupied, captured)
+    }
+    fn is_castling_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        matches!(self.get_piece(from.0, from.1), Piece::King(_))
+            && from.0 == to.0
+            && from.1.abs_diff(to.1) == 2
+    }
+    fn is_en_passant_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        matches!(self.get_piece(from.0, from.1), Piece::Pawn(_))
+            && self.en_passant_target == Some(to)
+            && from.1 != to.1
     }
     fn king_still_in_check(&self, color: PieceColor) -> bool {
         let king_position = self.get_king_position(color);
-        let attacking_color = color.opposite();
-        for row in 0..ROWS {
-            for col in 0..COLS {
-                if self.get_piece(row, col).color() == Some(attacking_color) {
-                    let legal_moves = self.get_valid_moves_to_consider((row, col));
-                    if legal_moves.contains(&king_position) {
-                        return true;
-                    }
-                }
-            }
+        self.is_square_attacked(king_position, color.opposite())
+    }
+    /// returns true if any piece of color `by` attacks the given square.
+    /// this ignores whose turn it is and counts pawn captures onto empty
+    /// squares, which is what castling needs.
+    pub fn is_square_attacked(&self, square: (usize, usize), by: PieceColor) -> bool {
+        self.is_attacked_after(square_index(square), by, self.all_pieces(), 0)
+    }
+    /// whether `by` attacks `square` with `occupied` as the pieces in the way,
+    /// leaving out any piece of theirs on `captured`
+    fn is_attacked_after(
+        &self,
+        square: usize,
+        by: PieceColor,
+        occupied: Bitboard,
+        captured: Bitboard,
+    ) -> bool {
+        let pieces = |kind: usize| self.pieces[by.index()][kind] & !captured;
+        // a pawn of `by` attacks the square from where a pawn of the other color
+        // standing on it would capture
+        pawn_attacks(by.opposite(), square) & pieces(PAWN) != 0
+            || knight_attacks(square) & pieces(KNIGHT) != 0
+            || king_attacks(square) & pieces(KING) != 0
+            || bishop_attacks(square, occupied) & (pieces(BISHOP) | pieces(QUEEN)) != 0
+            || rook_attacks(square, occupied) & (pieces(ROOK) | pieces(QUEEN)) != 0
+    }
+    fn king_square(&self, color: PieceColor) -> usize {
+        let king = self.pieces[color.index()][KING];
+        if king == 0 {
+            panic!("King not found");
         }
-        false
+        king.trailing_zeros() as usize
     }
     fn get_king_position(&self, color: PieceColor) -> (usize, usize) {
-        for row in 0..ROWS {
-            for col in 0..COLS {
-                if let Piece::King(k) = self.get_piece(row, col) {
-                    if k.color == color {
-                        println!("King found at ({}, {})", row, col);
-                        return (row, col);
-                    }
-                }
+        square_at(self.king_square(color))
+    }
+    /// returns true if the side to move is in check
+    pub fn is_in_check(&self) -> bool {
+        self.king_still_in_check(self.turn_player)
+    }
+    /// the square of the side to move's king if it is in check
+    pub fn checked_king(&self) -> Option<(usize, usize)> {
+        self.is_in_check()
+            .then(|| self.get_king_position(self.turn_player))
+    }
+    pub fn is_checkmate(&self) -> bool {
+        self.is_in_check() && !self.has_legal_move()
+    }
+    pub fn is_stalemate(&self) -> bool {
+        !self.is_in_check() && !self.has_legal_move()
+    }
+    fn has_legal_move(&self) -> bool {
+        squares(self.occupied[self.turn_player.index()]).any(|from| self.legal_targets(from) != 0)
+    }
+    /// plies played since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+    /// the number of the move being played, as written in move lists and FEN
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+    /// tells whether the game is still going, and if not, who won and why
+    pub fn game_status(&self) -> GameStatus {
+        if !self.has_legal_move() {
+            return GameStatus::Over(if self.is_in_check() {
+                GameResult::win(self.turn_player.opposite(), GameEndReason::Checkmate)
+            } else {
+                GameResult::draw(GameEndReason::Stalemate)
+            });
+        }
+        if self.halfmove_clock >= 100 {
+            return GameStatus::Over(GameResult::draw(GameEndReason::FiftyMoveRule));
+        }
+        if self.is_threefold_repetition() {
+            return GameStatus::Over(GameResult::draw(GameEndReason::ThreefoldRepetition));
+        }
+        if self.is_insufficient_material() {
+            return GameStatus::Over(GameResult::draw(GameEndReason::InsufficientMaterial));
+        }
+        GameStatus::Ongoing
+    }
+    /// returns true if the current position has occurred at least three times
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.position_history
+            .iter()
+            .filter(|&&key| key == self.hash)
+            .count()
+            >= 3
+    }
+    /// returns true if the current position has occurred before, which is all
+    /// a search needs to score it as a draw
+    pub fn is_repetition(&self) -> bool {
+        self.position_history
+            .iter()
+            .filter(|&&key| key == self.hash)
+            .count()
+            >= 2
+    }
+    /// returns true if neither side has enough material left to deliver mate:
+    /// bare kings, a single minor piece, or only bishops that all stand on one square color
+    pub fn is_insufficient_material(&self) -> bool {
+        let kinds = |kinds: &[usize]| -> Bitboard {
+            kinds
+                .iter()
+                .map(|&kind| self.pieces[0][kind] | self.pieces[1][kind])
+                .fold(0, |all, squares| all | squares)
+        };
+        if kinds(&[PAWN, ROOK, QUEEN]) != 0 {
+            return false;
+        }
+        let (knights, bishops) = (kinds(&[KNIGHT]), kinds(&[BISHOP]));
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+    /// whether `color` could mate by some series of legal moves, with the other side
+    /// helping. a lone minor piece can only mate a king hemmed in by its own men,
+    /// so it needs the other side to have something besides the king
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        if self.is_insufficient_material() {
+            return false;
+        }
+        let own = &self.pieces[color.index()];
+        if own[PAWN] | own[ROOK] | own[QUEEN] != 0 {
+            return true;
+        }
+        match (own[KNIGHT] | own[BISHOP]).count_ones() {
+            0 => false,
+            1 => {
+                let other = &self.pieces[color.opposite().index()];
+                other[PAWN] | other[KNIGHT] | other[BISHOP] | other[ROOK] | other[QUEEN] != 0
             }
+            _ => true,
         }
-        panic!("King not found");
     }
-    pub fn is_checkmate(&self) -> bool {
-        let cur_player = self.turn_player;
-        // check that there is not a single legal move for the current player
-        for row in 0..ROWS {
-            for col in 0..COLS {
-                if self.get_piece(row, col).color() == Some(cur_player) {
-                    let legal_moves = self.get_legal_moves((row, col));
-                    if !legal_moves.is_empty() {
-                        return false;
-                    }
-                }
+    /// the result when `flagged` runs out of time: a loss, unless the opponent
+    /// could never mate whatever `flagged` did
+    pub fn timeout_result(&self, flagged: PieceColor) -> GameResult {
+        let opponent = flagged.opposite();
+        if self.has_mating_material(opponent) {
+            GameResult::win(opponent, GameEndReason::Timeout)
+        } else {
+            GameResult::draw(GameEndReason::TimeoutVsInsufficientMaterial)
+        }
+    }
+    /// which castling moves are still available to each side, ignoring whether
+    /// they are legal right now
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+}
+/// Castling moves each side may still make at some point in the game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+impl CastlingRights {
+    const NONE: CastlingRights = CastlingRights {
+        white_kingside: false,
+        white_queenside: false,
+        black_kingside: false,
+        black_queenside: false,
+    };
+    const ALL: CastlingRights = CastlingRights {
+        white_kingside: true,
+        white_queenside: true,
+        black_kingside: true,
+        black_queenside: true,
+    };
+    /// drops the rights that need the king or rook starting on `square`
+    fn remove_for_square(&mut self, square: (usize, usize)) {
+        let white_row = home_row(PieceColor::White);
+        let black_row = home_row(PieceColor::Black);
+        match square {
+            (row, 4) if row == white_row => {
+                self.white_kingside = false;
+                self.white_queenside = false;
             }
+            (row, 4) if row == black_row => {
+                self.black_kingside = false;
+                self.black_queenside = false;
+            }
+            (row, col) if row == white_row && col == COLS - 1 => self.white_kingside = false,
+            (row, 0) if row == white_row => self.white_queenside = false,
+            (row, col) if row == black_row && col == COLS - 1 => self.black_kingside = false,
+            (row, 0) if row == black_row => self.black_queenside = false,
+            _ => (),
         }
-        true
     }
 }
 impl Default for Board {
     fn default() -> Self {
-        let board = STARTING_BOARD_STR
-            .iter()
-            .flatten()
-            .map(|&c| piece_from_char(c))
-            .collect::<Vec<Piece>>()
-            .try_into()
-            .unwrap();
-        Board {
-            board,
-            turn_player: PieceColor::White,
+        let mut board = Board::empty();
+        for (row, rank) in STARTING_BOARD_STR.iter().enumerate() {
+            for (col, &c) in rank.iter().enumerate() {
+                let piece = piece_from_char(c).expect("starting board only holds piece chars");
+                board.set_piece(row, col, piece);
+            }
         }
+        board.castling = CastlingRights::ALL;
+        board.hash = board.polyglot_key();
+        board.position_history.push(board.hash);
+        board
     }
 }
 
+/// the algebraic name of a square, e.g. `(7, 4)` is "e1"
+pub fn square_name((row, col): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + col as u8) as char, ROWS - row)
+}
+/// parses an algebraic square name like "e4" into `(row, col)`
+pub fn parse_square(name: &str) -> Option<(usize, usize)> {
+    let mut chars = name.chars();
+    let file = chars.next()?;
+    let rank = chars.next()?.to_digit(10)? as usize;
+    if chars.next().is_some() || !('a'..='h').contains(&file) || !(1..=ROWS).contains(&rank) {
+        return None;
+    }
+    Some((ROWS - rank, file as usize - 'a' as usize))
+}
+/// the row a color's king and rooks start on
+fn home_row(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => ROWS - 1,
+        PieceColor::Black => 0,
+    }
+}
+fn promotion_row(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => ROWS - 1,
+    }
+}
+/// returns the rook's `(from, to)` squares for a castling king move from `from` to `to`
+pub fn castling_rook_move(
+    from: (usize, usize),
+    to: (usize, usize),
+) -> ((usize, usize), (usize, usize)) {
+    let row = from.0;
+    if to.1 > from.1 {
+        ((row, COLS - 1), (row, to.1 - 1))
+    } else {
+        ((row, 0), (row, to.1 + 1))
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
 pub enum MoveType {
     Capture,
     Move,
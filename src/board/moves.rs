@@ -0,0 +1,61 @@
+use super::piece::{Piece, PromotionPiece};
+use super::san::{promotion_from_letter, promotion_letter};
+use super::{parse_square, square_name, Board, CastlingRights, MoveType};
+
+/// A single move, with enough recorded about it to take it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Move {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// the piece that moved, as it stood on `from` before the move
+    pub piece: Piece,
+    /// the piece that was taken, including a pawn taken en passant
+    pub captured: Option<Piece>,
+    /// what a pawn reaching the last rank turns into
+    pub promotion: Option<PromotionPiece>,
+    pub kind: MoveType,
+}
+impl Move {
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+    /// the move in the long algebraic notation UCI uses, e.g. "e2e4", "e7e8q" or
+    /// "e1g1" for castling
+    pub fn to_uci(&self) -> String {
+        let mut uci = square_name(self.from) + &square_name(self.to);
+        if let Some(promotion) = self.promotion {
+            uci.push(promotion_letter(promotion).to_ascii_lowercase());
+        }
+        uci
+    }
+}
+impl Board {
+    /// parses a move in UCI notation, returning `None` unless it is legal here.
+    /// a promotion without a piece letter is not a legal move
+    pub fn parse_uci_move(&self, uci: &str) -> Option<Move> {
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return None;
+        }
+        let from = parse_square(&uci[0..2])?;
+        let to = parse_square(&uci[2..4])?;
+        let promotion = match uci[4..].chars().next() {
+            Some(letter) => Some(promotion_from_letter(letter.to_ascii_uppercase())?),
+            None => None,
+        };
+        if !self.get_legal_moves(from).contains(&to)
+            || self.is_promotion_move(from, to) != promotion.is_some()
+        {
+            return None;
+        }
+        Some(self.build_move(from, to, promotion))
+    }
+}
+/// The parts of a `Board` a move overwrites and cannot work out again on undo.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct IrreversibleState {
+    pub(super) castling: CastlingRights,
+    pub(super) en_passant_target: Option<(usize, usize)>,
+    pub(super) halfmove_clock: u32,
+    pub(super) fullmove_number: u32,
+    pub(super) hash: u64,
+}
@@ -0,0 +1,44 @@
+use super::moves::Move;
+use super::Board;
+
+impl Board {
+    /// counts the positions reached after every sequence of `depth` legal moves,
+    /// the usual way to check move generation against published numbers
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.position_copy().perft_in_place(depth)
+    }
+    /// the perft count below each legal move, to find which move a wrong total
+    /// comes from. `depth` counts the move itself
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut board = self.position_copy();
+        board
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let state = board.apply_move(&mv);
+                let nodes = board.perft_in_place(depth.saturating_sub(1));
+                board.unapply_move(&mv, state);
+                (mv, nodes)
+            })
+            .collect()
+    }
+    /// plays the moves with `apply_move` so no history or position keys are kept
+    fn perft_in_place(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        moves
+            .iter()
+            .map(|mv| {
+                let state = self.apply_move(mv);
+                let nodes = self.perft_in_place(depth - 1);
+                self.unapply_move(mv, state);
+                nodes
+            })
+            .sum()
+    }
+}
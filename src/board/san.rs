@@ -0,0 +1,195 @@
+use std::fmt;
+
+use super::moves::Move;
+use super::piece::{Piece, PromotionPiece};
+use super::{parse_square, square_name, Board};
+
+/// Why a SAN string could not be turned into a move.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SanError {
+    /// the string is not written like a move at all
+    Syntax(String),
+    /// no legal move in the position matches it
+    Illegal(String),
+    /// more than one legal move matches it
+    Ambiguous(String),
+}
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Syntax(san) => write!(f, "'{}' is not a move in SAN", san),
+            SanError::Illegal(san) => write!(f, "{} is not a legal move here", san),
+            SanError::Ambiguous(san) => write!(f, "{} could mean more than one move", san),
+        }
+    }
+}
+impl std::error::Error for SanError {}
+
+impl Board {
+    /// writes a legal move in Standard Algebraic Notation, e.g. "Nbd7", "exd6", "e8=Q+" or "O-O".
+    /// the move is played and taken back to see whether it checks, leaving the board as it was
+    pub fn move_to_san(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: Option<PromotionPiece>,
+    ) -> String {
+        let piece = self.get_piece(from.0, from.1);
+        let mut san = if self.is_castling_move(from, to) {
+            if to.1 > from.1 {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        } else {
+            let is_capture =
+                !self.get_piece(to.0, to.1).is_empty() || self.is_en_passant_move(from, to);
+            let mut san = String::new();
+            match piece_letter(piece) {
+                Some(letter) => {
+                    san.push(letter);
+                    san.push_str(&self.disambiguation(from, to));
+                }
+                // pawn captures always name the file they came from
+                None if is_capture => san.push(square_name(from).chars().next().unwrap()),
+                None => (),
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&square_name(to));
+            if self.is_promotion_move(from, to) {
+                san.push('=');
+                san.push(promotion_letter(promotion.unwrap_or(PromotionPiece::Queen)));
+            }
+            san
+        };
+        self.make_move(self.build_move(from, to, promotion));
+        if self.is_in_check() {
+            san.push(if self.is_checkmate() { '#' } else { '+' });
+        }
+        self.undo_move();
+        san
+    }
+    /// the smallest prefix of the origin square that tells this move apart from
+    /// the same kind of piece reaching `to` from elsewhere
+    fn disambiguation(&self, from: (usize, usize), to: (usize, usize)) -> String {
+        let piece = self.get_piece(from.0, from.1);
+        let rivals: Vec<(usize, usize)> = self
+            .get_all_legal_moves()
+            .into_iter()
+            .filter(|&(other, other_to)| {
+                other != from
+                    && other_to == to
+                    && piece_letter(self.get_piece(other.0, other.1)) == piece_letter(piece)
+            })
+            .map(|(other, _)| other)
+            .collect();
+        let name = square_name(from);
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals.iter().all(|&(_, col)| col != from.1) {
+            name[..1].to_string()
+        } else if rivals.iter().all(|&(row, _)| row != from.0) {
+            name[1..].to_string()
+        } else {
+            name
+        }
+    }
+    /// finds the legal move a SAN string describes. check and annotation
+    /// suffixes are ignored, castling may be written with zeros and the
+    /// promotion piece may leave out the '=', as in "e8Q".
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let syntax = || SanError::Syntax(san.to_string());
+        let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.get_all_legal_moves();
+        if matches!(trimmed, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let kingside = trimmed.len() == 3;
+            return legal_moves
+                .into_iter()
+                .find(|&(from, to)| self.is_castling_move(from, to) && (to.1 > from.1) == kingside)
+                .map(|(from, to)| self.build_move(from, to, None))
+                .ok_or_else(|| SanError::Illegal(san.to_string()));
+        }
+
+        let mut body = trimmed;
+        let mut promotion = None;
+        if let Some((rest, piece)) = body.split_once('=') {
+            let mut letters = piece.chars();
+            promotion = letters.next().and_then(promotion_from_letter);
+            if promotion.is_none() || letters.next().is_some() {
+                return Err(syntax());
+            }
+            body = rest;
+        } else if let Some(p) = body.chars().last().and_then(promotion_from_letter) {
+            promotion = Some(p);
+            body = &body[..body.len() - 1];
+        }
+        let letter = body.chars().next().filter(|c| "NBRQK".contains(*c));
+        if letter.is_some() {
+            body = &body[1..];
+        }
+        if body.len() < 2 || !body.is_ascii() {
+            return Err(syntax());
+        }
+        let to = parse_square(&body[body.len() - 2..]).ok_or_else(syntax)?;
+        let hint = body[..body.len() - 2].trim_end_matches('x');
+        let mut hint_col = None;
+        let mut hint_row = None;
+        for c in hint.chars() {
+            match c {
+                'a'..='h' => hint_col = Some(c as usize - 'a' as usize),
+                '1'..='8' => hint_row = parse_square(&format!("a{}", c)).map(|(row, _)| row),
+                _ => return Err(syntax()),
+            }
+        }
+
+        let candidates: Vec<((usize, usize), (usize, usize))> = legal_moves
+            .into_iter()
+            .filter(|&(from, move_to)| {
+                move_to == to
+                    && piece_letter(self.get_piece(from.0, from.1)) == letter
+                    && hint_col.is_none_or(|col| col == from.1)
+                    && hint_row.is_none_or(|row| row == from.0)
+            })
+            .collect();
+        let (from, to) = match candidates[..] {
+            [single] => single,
+            [] => return Err(SanError::Illegal(san.to_string())),
+            _ => return Err(SanError::Ambiguous(san.to_string())),
+        };
+        if promotion.is_some() != self.is_promotion_move(from, to) {
+            return Err(SanError::Illegal(san.to_string()));
+        }
+        Ok(self.build_move(from, to, promotion))
+    }
+}
+
+/// the SAN letter of a piece, `None` for pawns
+fn piece_letter(piece: &Piece) -> Option<char> {
+    match piece {
+        Piece::Knight(_) => Some('N'),
+        Piece::Bishop(_) => Some('B'),
+        Piece::Rook(_) => Some('R'),
+        Piece::Queen(_) => Some('Q'),
+        Piece::King(_) => Some('K'),
+        Piece::Pawn(_) | Piece::Empty(_) => None,
+    }
+}
+pub(super) fn promotion_letter(promotion: PromotionPiece) -> char {
+    match promotion {
+        PromotionPiece::Knight => 'N',
+        PromotionPiece::Bishop => 'B',
+        PromotionPiece::Rook => 'R',
+        PromotionPiece::Queen => 'Q',
+    }
+}
+pub(super) fn promotion_from_letter(c: char) -> Option<PromotionPiece> {
+    match c {
+        'N' => Some(PromotionPiece::Knight),
+        'B' => Some(PromotionPiece::Bishop),
+        'R' => Some(PromotionPiece::Rook),
+        'Q' => Some(PromotionPiece::Queen),
+        _ => None,
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+use super::piece::PieceColor;
+
+/// Whether the game on a `Board` can go on, and how it ended if not.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameStatus {
+    Ongoing,
+    Over(GameResult),
+}
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        matches!(self, GameStatus::Over(_))
+    }
+}
+/// The outcome of a finished game. `winner` is `None` for draws.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameResult {
+    pub winner: Option<PieceColor>,
+    pub reason: GameEndReason,
+}
+impl GameResult {
+    pub fn win(winner: PieceColor, reason: GameEndReason) -> GameResult {
+        GameResult {
+            winner: Some(winner),
+            reason,
+        }
+    }
+    pub fn draw(reason: GameEndReason) -> GameResult {
+        GameResult {
+            winner: None,
+            reason,
+        }
+    }
+    /// the result as PGN writes it: "1-0", "0-1" or "1/2-1/2"
+    pub fn pgn_result(&self) -> &'static str {
+        match self.winner {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.winner {
+            Some(PieceColor::White) => write!(f, "White wins by {}", self.reason),
+            Some(PieceColor::Black) => write!(f, "Black wins by {}", self.reason),
+            None => write!(f, "Draw by {}", self.reason),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEndReason {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+    /// the side to move ran out of time
+    Timeout,
+    /// the side to move ran out of time, but the other side could never have mated
+    TimeoutVsInsufficientMaterial,
+}
+impl fmt::Display for GameEndReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            GameEndReason::Checkmate => "checkmate",
+            GameEndReason::Stalemate => "stalemate",
+            GameEndReason::FiftyMoveRule => "the fifty-move rule",
+            GameEndReason::ThreefoldRepetition => "threefold repetition",
+            GameEndReason::InsufficientMaterial => "insufficient material",
+            GameEndReason::Timeout => "timeout",
+            GameEndReason::TimeoutVsInsufficientMaterial => "timeout against insufficient material",
+        };
+        write!(f, "{}", reason)
+    }
+}
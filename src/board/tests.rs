@@ -0,0 +1,484 @@
+use super::piece::{Piece, PieceColor, PromotionPiece};
+use super::status::{GameEndReason, GameResult, GameStatus};
+use super::{parse_square, square_name, Board, CastlingRights};
+
+fn board(fen: &str) -> Board {
+    Board::from_fen(fen).unwrap_or_else(|e| panic!("{}: {}", fen, e))
+}
+fn square(name: &str) -> (usize, usize) {
+    parse_square(name).unwrap()
+}
+/// plays the moves given in UCI notation
+fn play(board: &mut Board, moves: &[&str]) {
+    for uci in moves {
+        let mv = board
+            .parse_uci_move(uci)
+            .unwrap_or_else(|| panic!("{} is not legal", uci));
+        board.make_move(mv);
+    }
+}
+/// the squares the piece on `from` can legally move to, by name
+fn targets(board: &Board, from: &str) -> Vec<String> {
+    let mut targets: Vec<String> = board
+        .get_legal_moves(square(from))
+        .into_iter()
+        .map(square_name)
+        .collect();
+    targets.sort();
+    targets
+}
+fn can_move(board: &Board, from: &str, to: &str) -> bool {
+    targets(board, from).iter().any(|t| t == to)
+}
+/// the piece on the named square
+fn piece_at(board: &Board, name: &str) -> Piece {
+    let (row, col) = square(name);
+    board.get_piece(row, col).clone()
+}
+fn is_rook(board: &Board, name: &str, color: PieceColor) -> bool {
+    let (row, col) = square(name);
+    matches!(board.get_piece(row, col), Piece::Rook(r) if r.color == color)
+}
+
+#[test]
+fn castling_moves_the_rook_too() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(can_move(&b, "e1", "g1"));
+    assert!(can_move(&b, "e1", "c1"));
+    play(&mut b, &["e1g1"]);
+    assert!(is_rook(&b, "f1", PieceColor::White));
+    assert!(b.get_piece(7, 7).is_empty());
+    play(&mut b, &["e8c8"]);
+    assert!(is_rook(&b, "d8", PieceColor::Black));
+    assert!(b.get_piece(0, 0).is_empty());
+    assert_eq!(b.castling_rights(), CastlingRights::NONE);
+}
+
+#[test]
+fn no_castling_out_of_check() {
+    let b = board("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn no_castling_through_check() {
+    let b = board("5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(can_move(&b, "e1", "c1"));
+    let b = board("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert!(can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn no_castling_into_check() {
+    let b = board("6rk/7p/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn queenside_castling_ignores_an_attack_on_the_rook_path() {
+    // b1 is attacked, but only the rook crosses it
+    let b = board("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    assert!(can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn no_castling_through_pieces() {
+    let b = board("4k3/8/8/8/8/8/8/RN2K1NR w KQ - 0 1");
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+    // a piece next to the rook blocks queenside castling as well
+    let b = board("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1");
+    assert!(can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn no_castling_without_the_right() {
+    let b = board("r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1");
+    assert!(can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn moving_the_king_loses_both_rights() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    play(&mut b, &["e1d1", "e8d8", "d1e1", "d8e8"]);
+    assert_eq!(b.castling_rights(), CastlingRights::NONE);
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(!can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn moving_a_rook_loses_its_side() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    play(&mut b, &["h1h2", "a8a7", "h2h1", "a7a8"]);
+    assert_eq!(
+        b.castling_rights(),
+        CastlingRights {
+            white_kingside: false,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: false,
+        }
+    );
+    assert!(!can_move(&b, "e1", "g1"));
+    assert!(can_move(&b, "e1", "c1"));
+}
+
+#[test]
+fn capturing_a_rook_loses_its_side() {
+    let mut b = board("r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1");
+    play(&mut b, &["g2h1"]);
+    assert!(!b.castling_rights().white_kingside);
+    assert!(b.castling_rights().white_queenside);
+    assert!(!can_move(&b, "e1", "g1"));
+}
+
+#[test]
+fn undo_restores_castling() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    play(&mut b, &["e1g1"]);
+    b.undo_move();
+    assert_eq!(b.castling_rights(), CastlingRights::ALL);
+    assert!(is_rook(&b, "h1", PieceColor::White));
+    assert!(b.get_piece(7, 5).is_empty());
+    assert_eq!(b.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+}
+
+#[test]
+fn en_passant_takes_the_passed_pawn() {
+    let mut b = Board::default();
+    play(&mut b, &["e2e4", "a7a6", "e4e5", "d7d5"]);
+    assert_eq!(b.en_passant_target(), Some(square("d6")));
+    assert!(can_move(&b, "e5", "d6"));
+    play(&mut b, &["e5d6"]);
+    assert!(b.get_piece(3, 3).is_empty());
+    assert_eq!(b.en_passant_target(), None);
+}
+
+#[test]
+fn en_passant_only_on_the_next_move() {
+    let mut b = Board::default();
+    play(&mut b, &["e2e4", "a7a6", "e4e5", "d7d5", "h2h3", "h7h6"]);
+    assert_eq!(b.en_passant_target(), None);
+    assert!(!can_move(&b, "e5", "d6"));
+}
+
+#[test]
+fn no_en_passant_exposing_the_king_along_the_rank() {
+    // both pawns leave the fifth rank, opening it for the rook
+    let b = board("8/8/8/K2pP2r/8/8/8/4k3 w - d6 0 1");
+    assert!(!can_move(&b, "e5", "d6"));
+    assert!(can_move(&b, "e5", "e6"));
+}
+
+#[test]
+fn en_passant_can_take_a_checking_pawn() {
+    let b = board("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+    assert!(b.is_in_check());
+    assert!(can_move(&b, "e4", "d3"));
+}
+
+#[test]
+fn undo_restores_en_passant() {
+    let mut b = Board::default();
+    play(&mut b, &["e2e4", "a7a6", "e4e5", "d7d5"]);
+    let fen = b.to_fen();
+    play(&mut b, &["e5d6"]);
+    b.undo_move();
+    assert_eq!(b.en_passant_target(), Some(square("d6")));
+    assert!(can_move(&b, "e5", "d6"));
+    assert_eq!(b.to_fen(), fen);
+    // and undoing a quiet move brings it back as well
+    play(&mut b, &["b1c3"]);
+    b.undo_move();
+    assert_eq!(b.en_passant_target(), Some(square("d6")));
+}
+
+#[test]
+fn promotion_offers_every_piece() {
+    let b = board("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    let mut promotions: Vec<String> = b
+        .legal_moves()
+        .into_iter()
+        .filter(|mv| mv.from == square("b7"))
+        .map(|mv| mv.to_uci())
+        .collect();
+    promotions.sort();
+    assert_eq!(promotions, ["b7b8b", "b7b8n", "b7b8q", "b7b8r"]);
+    // the piece has to be named
+    assert!(b.parse_uci_move("b7b8").is_none());
+}
+
+#[test]
+fn underpromotion() {
+    let mut b = board("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    for (uci, promotion) in [
+        ("b7b8n", PromotionPiece::Knight),
+        ("b7b8b", PromotionPiece::Bishop),
+        ("b7b8r", PromotionPiece::Rook),
+    ] {
+        play(&mut b, &[uci]);
+        assert_eq!(piece_at(&b, "b8"), promotion.to_piece(PieceColor::White));
+        b.undo_move();
+        assert!(matches!(piece_at(&b, "b7"), Piece::Pawn(_)));
+    }
+    let mut b = board("4k3/8/8/8/8/8/p7/4K3 b - - 0 1");
+    play(&mut b, &["a2a1r"]);
+    assert!(is_rook(&b, "a1", PieceColor::Black));
+}
+
+#[test]
+fn knight_promotion_can_give_check_where_a_queen_would_not() {
+    let mut b = board("8/5P1k/8/8/8/8/8/4K3 w - - 0 1");
+    play(&mut b, &["f7f8n"]);
+    assert!(b.is_in_check());
+    b.undo_move();
+    play(&mut b, &["f7f8q"]);
+    assert!(!b.is_in_check());
+}
+
+#[test]
+fn promotion_with_capture() {
+    let mut b = board("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    let fen = b.to_fen();
+    assert!(can_move(&b, "a7", "a8"));
+    assert!(can_move(&b, "a7", "b8"));
+    play(&mut b, &["a7b8q"]);
+    let mv = b.last_move().unwrap();
+    assert_eq!(mv.captured, Some(piece_at(&board(&fen), "b8")));
+    assert_eq!(mv.promotion, Some(PromotionPiece::Queen));
+    assert_eq!(
+        piece_at(&b, "b8"),
+        PromotionPiece::Queen.to_piece(PieceColor::White)
+    );
+    assert!(piece_at(&b, "a7").is_empty());
+    assert_eq!(b.halfmove_clock(), 0);
+    b.undo_move();
+    assert_eq!(b.to_fen(), fen);
+    assert!(is_rook(&b, "b8", PieceColor::Black));
+}
+
+#[test]
+fn promotion_capturing_a_rook_loses_its_castling_right() {
+    let mut b = board("r3k2r/1P6/8/8/8/8/8/4K3 w kq - 0 1");
+    play(&mut b, &["b7a8n"]);
+    assert!(!b.castling_rights().black_queenside);
+    assert!(b.castling_rights().black_kingside);
+}
+
+#[test]
+fn checkmate() {
+    let mut b = Board::default();
+    play(&mut b, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    assert!(b.is_checkmate());
+    assert!(!b.is_stalemate());
+    assert_eq!(
+        b.game_status(),
+        GameStatus::Over(GameResult::win(PieceColor::Black, GameEndReason::Checkmate))
+    );
+    let mut b = board("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    play(&mut b, &["a1a8"]);
+    assert!(b.is_checkmate());
+    assert_eq!(
+        b.game_status(),
+        GameStatus::Over(GameResult::win(PieceColor::White, GameEndReason::Checkmate))
+    );
+}
+
+#[test]
+fn stalemate() {
+    for fen in [
+        "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+        "k7/P7/K7/8/8/8/8/8 b - - 0 1",
+    ] {
+        let b = board(fen);
+        assert!(b.is_stalemate(), "{}", fen);
+        assert!(!b.is_checkmate(), "{}", fen);
+        assert_eq!(
+            b.game_status(),
+            GameStatus::Over(GameResult::draw(GameEndReason::Stalemate)),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn check_with_a_way_out_is_neither() {
+    let mut b = board("7k/8/8/8/8/8/8/R3K3 w - - 0 1");
+    play(&mut b, &["a1a8"]);
+    assert!(b.is_in_check());
+    assert!(!b.is_checkmate());
+    assert!(!b.is_stalemate());
+    assert_eq!(b.game_status(), GameStatus::Ongoing);
+    // the side not to move having no moves is no stalemate either
+    let b = board("k7/P7/K7/8/8/8/8/8 w - - 0 1");
+    assert!(!b.is_stalemate());
+}
+
+#[test]
+fn fifty_move_rule() {
+    let mut b = board("4k3/8/8/8/8/8/8/R3K3 w - - 99 80");
+    assert_eq!(b.game_status(), GameStatus::Ongoing);
+    play(&mut b, &["a1a2"]);
+    assert_eq!(b.halfmove_clock(), 100);
+    assert_eq!(
+        b.game_status(),
+        GameStatus::Over(GameResult::draw(GameEndReason::FiftyMoveRule))
+    );
+    b.undo_move();
+    assert_eq!(b.halfmove_clock(), 99);
+    assert_eq!(b.game_status(), GameStatus::Ongoing);
+}
+
+#[test]
+fn pawn_moves_and_captures_reset_the_fifty_move_count() {
+    let mut b = board("4k3/8/8/8/8/1p6/P7/4K3 w - - 99 80");
+    play(&mut b, &["a2a3"]);
+    assert_eq!(b.halfmove_clock(), 0);
+    let mut b = board("4k3/8/8/8/8/1p6/P7/4K3 w - - 99 80");
+    play(&mut b, &["a2b3"]);
+    assert_eq!(b.halfmove_clock(), 0);
+    assert_eq!(b.game_status(), GameStatus::Ongoing);
+}
+
+#[test]
+fn mate_on_the_hundredth_ply_is_still_mate() {
+    let mut b = board("7k/8/6K1/8/8/8/8/R7 w - - 99 80");
+    play(&mut b, &["a1a8"]);
+    assert_eq!(
+        b.game_status(),
+        GameStatus::Over(GameResult::win(PieceColor::White, GameEndReason::Checkmate))
+    );
+}
+
+#[test]
+fn threefold_repetition() {
+    let mut b = Board::default();
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    play(&mut b, &shuffle);
+    assert!(b.is_repetition());
+    assert!(!b.is_threefold_repetition());
+    play(&mut b, &shuffle[..3]);
+    assert!(!b.is_threefold_repetition());
+    play(&mut b, &shuffle[3..]);
+    assert!(b.is_threefold_repetition());
+    assert_eq!(
+        b.game_status(),
+        GameStatus::Over(GameResult::draw(GameEndReason::ThreefoldRepetition))
+    );
+}
+
+#[test]
+fn undo_forgets_repetitions() {
+    let mut b = Board::default();
+    let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+    play(&mut b, &shuffle);
+    play(&mut b, &shuffle);
+    assert!(b.is_threefold_repetition());
+    b.undo_move();
+    b.undo_move();
+    assert!(!b.is_threefold_repetition());
+    assert_eq!(b.game_status(), GameStatus::Ongoing);
+    // playing the same moves again brings the repetition back
+    play(&mut b, &shuffle[2..]);
+    assert!(b.is_threefold_repetition());
+}
+
+#[test]
+fn lost_castling_rights_make_a_different_position() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let shuffle = ["e1f1", "e8f8", "f1e1", "f8e8"];
+    play(&mut b, &shuffle);
+    play(&mut b, &shuffle);
+    // the first time round both sides could still castle
+    assert!(!b.is_threefold_repetition());
+    play(&mut b, &shuffle);
+    assert!(b.is_threefold_repetition());
+}
+
+#[test]
+fn insufficient_material() {
+    for fen in [
+        // bare kings
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        // a single bishop or knight
+        "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4K1n1 w - - 0 1",
+        // bishops that all stand on dark squares
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4kb2/8/8/8/8/8/1B6/2B1K3 w - - 0 1",
+    ] {
+        let b = board(fen);
+        assert!(b.is_insufficient_material(), "{}", fen);
+        assert_eq!(
+            b.game_status(),
+            GameStatus::Over(GameResult::draw(GameEndReason::InsufficientMaterial)),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn sufficient_material() {
+    for fen in [
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+        // bishops on both square colors
+        "4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/2BBK3 w - - 0 1",
+        // a knight and anything else
+        "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/1N2K1n1 w - - 0 1",
+    ] {
+        let b = board(fen);
+        assert!(!b.is_insufficient_material(), "{}", fen);
+        assert_eq!(b.game_status(), GameStatus::Ongoing, "{}", fen);
+    }
+}
+
+#[test]
+fn timeout_against_a_bare_king_is_a_draw() {
+    let draw = GameResult::draw(GameEndReason::TimeoutVsInsufficientMaterial);
+    for fen in [
+        "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        // a lone minor piece against a bare king
+        "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+        // the flagged side's bishop stands on the same color, so no block helps
+        "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+    ] {
+        let b = board(fen);
+        assert!(!b.has_mating_material(PieceColor::White), "{}", fen);
+        assert_eq!(b.timeout_result(PieceColor::Black), draw, "{}", fen);
+    }
+    // a bare king cannot win on time against anything
+    let b = board("3qk3/8/8/8/8/8/8/4K3 w - - 0 1");
+    assert!(!b.has_mating_material(PieceColor::White));
+    assert_eq!(b.timeout_result(PieceColor::Black), draw);
+}
+
+#[test]
+fn timeout_with_mating_material_is_a_loss() {
+    let white_wins = GameResult::win(PieceColor::White, GameEndReason::Timeout);
+    for fen in [
+        // a lone minor piece can mate a king walled in by its own queen or rook
+        "3qk3/8/8/8/8/8/8/4KN2 w - - 0 1",
+        "3rk3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+        // or by a pawn, and a pawn of its own can always promote
+        "4k3/4p3/8/8/8/8/8/4KN2 w - - 0 1",
+        "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+    ] {
+        let b = board(fen);
+        assert!(b.has_mating_material(PieceColor::White), "{}", fen);
+        assert_eq!(b.timeout_result(PieceColor::Black), white_wins, "{}", fen);
+    }
+}
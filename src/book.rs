@@ -0,0 +1,262 @@
+//! Reading and writing opening books in the Polyglot `.bin` format.
+//!
+//! A book is a run of 16-byte big-endian entries sorted by position key: the
+//! key (see `Board::polyglot_key`), a move, a weight and a learn value that we
+//! carry along but never use.
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+
+use crate::board::fen::FenError;
+use crate::board::moves::Move;
+use crate::board::piece::{Piece, PromotionPiece};
+use crate::board::{square_name, Board, MoveType};
+use crate::consts::ROWS;
+use crate::pgn::PgnGame;
+
+const ENTRY_SIZE: usize = 16;
+
+/// A book move for a position, as it is stored in the book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookEntry {
+    pub mv: Move,
+    /// how often the move should be picked relative to the others
+    pub weight: u16,
+    pub learn: u32,
+}
+/// Why a book file could not be read.
+#[derive(Debug)]
+pub enum BookError {
+    Io(io::Error),
+    /// the file does not hold a whole number of entries
+    InvalidLength(usize),
+}
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::Io(e) => write!(f, "{}", e),
+            BookError::InvalidLength(length) => write!(
+                f,
+                "a book is made of {}-byte entries, found {} bytes",
+                ENTRY_SIZE, length
+            ),
+        }
+    }
+}
+impl std::error::Error for BookError {}
+impl From<io::Error> for BookError {
+    fn from(e: io::Error) -> Self {
+        BookError::Io(e)
+    }
+}
+
+/// One entry as stored in the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct RawEntry {
+    key: u64,
+    mv: u16,
+    weight: u16,
+    learn: u32,
+}
+impl RawEntry {
+    fn read(bytes: &[u8]) -> RawEntry {
+        let word = |range: std::ops::Range<usize>| {
+            bytes[range]
+                .iter()
+                .fold(0u64, |word, &byte| word << 8 | byte as u64)
+        };
+        RawEntry {
+            key: word(0..8),
+            mv: word(8..10) as u16,
+            weight: word(10..12) as u16,
+            learn: word(12..16) as u32,
+        }
+    }
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.key.to_be_bytes());
+        out.extend_from_slice(&self.mv.to_be_bytes());
+        out.extend_from_slice(&self.weight.to_be_bytes());
+        out.extend_from_slice(&self.learn.to_be_bytes());
+    }
+}
+
+/// A Polyglot book, held in memory.
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    /// sorted by key, so the entries of a position sit together
+    entries: Vec<RawEntry>,
+}
+impl Book {
+    pub fn open(path: &Path) -> Result<Book, BookError> {
+        Book::from_bytes(&std::fs::read(path)?)
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Book, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::InvalidLength(bytes.len()));
+        }
+        let mut entries: Vec<RawEntry> = bytes.chunks(ENTRY_SIZE).map(RawEntry::read).collect();
+        // books are written sorted, this only costs a pass over them
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Book { entries })
+    }
+    /// the number of entries over all positions
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// every book move for the position, heaviest first. entries whose move is
+    /// not legal on the board are left out
+    pub fn entries(&self, board: &Board) -> Vec<BookEntry> {
+        let key = board.hash();
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let mut entries: Vec<BookEntry> = self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                Some(BookEntry {
+                    mv: decode_move(board, entry.mv)?,
+                    weight: entry.weight,
+                    learn: entry.learn,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.weight));
+        entries
+    }
+    /// a book move picked at random, each with a chance in proportion to its weight
+    pub fn pick_move(&self, board: &Board) -> Option<Move> {
+        // every `RandomState` is seeded differently, which is all the
+        // randomness this needs
+        let roll = RandomState::new().build_hasher().finish();
+        self.weighted_move(board, roll)
+    }
+    /// the book move `roll` lands on when the weights are laid end to end.
+    /// moves with no weight are never picked, so `None` if all of them have none
+    pub fn weighted_move(&self, board: &Board, roll: u64) -> Option<Move> {
+        let entries = self.entries(board);
+        let total: u64 = entries.iter().map(|entry| entry.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = roll % total;
+        for entry in entries {
+            if roll < entry.weight as u64 {
+                return Some(entry.mv);
+            }
+            roll -= entry.weight as u64;
+        }
+        None
+    }
+}
+
+/// Collects the moves played in a set of games into a book.
+pub struct BookBuilder {
+    /// how many plies of each game go into the book
+    max_ply: usize,
+    /// how many games a move has to be played in to make it into the book
+    min_count: u32,
+    /// times each move was played, by position key and move
+    counts: HashMap<(u64, u16), u32>,
+}
+impl BookBuilder {
+    pub fn new(max_ply: usize, min_count: u32) -> BookBuilder {
+        BookBuilder {
+            max_ply,
+            min_count,
+            counts: HashMap::new(),
+        }
+    }
+    /// counts the first `max_ply` moves of the game's main line
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<(), FenError> {
+        let mut board = game.starting_board()?;
+        for pgn_move in game.moves.iter().take(self.max_ply) {
+            let mv = board.build_move(pgn_move.from, pgn_move.to, pgn_move.promotion);
+            *self
+                .counts
+                .entry((board.hash(), encode_move(&mv)))
+                .or_default() += 1;
+            board.make_move(mv);
+        }
+        Ok(())
+    }
+    /// the book in Polyglot format. a move's weight is the number of games it
+    /// was played in, scaled down if some move was played more often than a
+    /// weight can say
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let kept: Vec<(&(u64, u16), &u32)> = self
+            .counts
+            .iter()
+            .filter(|(_, &count)| count >= self.min_count)
+            .collect();
+        let most = kept.iter().map(|(_, &count)| count).max().unwrap_or(0);
+        let scale = (most as f64 / u16::MAX as f64).max(1.0);
+        let mut entries: Vec<RawEntry> = kept
+            .into_iter()
+            .map(|(&(key, mv), &count)| RawEntry {
+                key,
+                mv,
+                weight: ((count as f64 / scale).round() as u16).max(1),
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.key, std::cmp::Reverse(entry.weight), entry.mv));
+        let mut bytes = Vec::with_capacity(entries.len() * ENTRY_SIZE);
+        for entry in entries {
+            entry.write(&mut bytes);
+        }
+        bytes
+    }
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+/// packs a move the Polyglot way: to file and rank, from file and rank, then
+/// the promotion piece, three bits each. castling is written as the king
+/// taking its own rook
+fn encode_move(mv: &Move) -> u16 {
+    let to = match mv.kind {
+        MoveType::Castle if mv.to.1 > mv.from.1 => (mv.to.0, 7),
+        MoveType::Castle => (mv.to.0, 0),
+        _ => mv.to,
+    };
+    let square = |(row, col): (usize, usize)| ((ROWS - 1 - row) * 8 + col) as u16;
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(PromotionPiece::Knight) => 1,
+        Some(PromotionPiece::Bishop) => 2,
+        Some(PromotionPiece::Rook) => 3,
+        Some(PromotionPiece::Queen) => 4,
+    };
+    promotion << 12 | square(mv.from) << 6 | square(to)
+}
+/// unpacks a Polyglot move, `None` if it is not legal on the board
+fn decode_move(board: &Board, raw: u16) -> Option<Move> {
+    let square = |bits: u16| (ROWS - 1 - (bits >> 3 & 7) as usize, (bits & 7) as usize);
+    let from = square(raw >> 6);
+    let mut to = square(raw);
+    let castles = matches!(board.get_piece(from.0, from.1), Piece::King(_))
+        && matches!(board.get_piece(to.0, to.1), Piece::Rook(_))
+        && board.get_piece(from.0, from.1).color() == board.get_piece(to.0, to.1).color();
+    if castles {
+        to.1 = if to.1 > from.1 {
+            from.1 + 2
+        } else {
+            from.1 - 2
+        };
+    }
+    let promotion = match raw >> 12 & 7 {
+        0 => "",
+        1 => "n",
+        2 => "b",
+        3 => "r",
+        4 => "q",
+        _ => return None,
+    };
+    board.parse_uci_move(&(square_name(from) + &square_name(to) + promotion))
+}
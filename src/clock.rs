@@ -0,0 +1,350 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::board::piece::PieceColor;
+
+/// Where a `Clock` reads the time from. The clock only ever looks at differences
+/// between two readings, so any monotonic origin works.
+pub trait TimeSource {
+    fn now(&self) -> Duration;
+}
+/// The real, monotonic time since the source was created.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemTime {
+    origin: Instant,
+}
+impl SystemTime {
+    pub fn new() -> SystemTime {
+        SystemTime {
+            origin: Instant::now(),
+        }
+    }
+}
+impl Default for SystemTime {
+    fn default() -> Self {
+        SystemTime::new()
+    }
+}
+impl TimeSource for SystemTime {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+/// A time source that only moves when told to. Clones share the same time, so
+/// one clone can be handed to a `Clock` and the other kept to advance it.
+#[derive(Debug, Clone, Default)]
+pub struct ManualTime {
+    now: Rc<Cell<Duration>>,
+}
+impl ManualTime {
+    pub fn new() -> ManualTime {
+        ManualTime::default()
+    }
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+/// What a player gets back for each move they complete.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Increment {
+    None,
+    /// added after every move
+    Fischer(Duration),
+    /// the time used on the move is given back, up to this much
+    Bronstein(Duration),
+}
+/// One period of a time control: `time` for `moves` moves, or for the rest of the
+/// game if `moves` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControlStage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub increment: Increment,
+}
+/// The stages of a time control in order. A last stage with a move count repeats,
+/// so `40/90` alone means 90 minutes for every 40 moves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    pub stages: Vec<TimeControlStage>,
+}
+impl TimeControl {
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::None)
+    }
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::Fischer(increment))
+    }
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::single(time, Increment::Bronstein(delay))
+    }
+    /// panics if `stages` is empty or a stage is for zero moves
+    pub fn multi_stage(stages: Vec<TimeControlStage>) -> TimeControl {
+        assert!(
+            !stages.is_empty(),
+            "a time control needs at least one stage"
+        );
+        assert!(
+            stages.iter().all(|stage| stage.moves != Some(0)),
+            "a time control stage needs at least one move"
+        );
+        TimeControl { stages }
+    }
+    fn single(time: Duration, increment: Increment) -> TimeControl {
+        TimeControl {
+            stages: vec![TimeControlStage {
+                moves: None,
+                time,
+                increment,
+            }],
+        }
+    }
+    /// the stage that is in effect after `moves_made` completed moves, and whether
+    /// `moves_made` is exactly where it starts. a stage for zero moves, which only
+    /// `stages` being public allows, is taken to last for the rest of the game
+    fn stage_at(&self, moves_made: u32) -> (&TimeControlStage, bool) {
+        let mut stage_start = 0;
+        let mut index = 0;
+        loop {
+            let stage = &self.stages[index];
+            let Some(moves) = stage.moves.filter(|&moves| moves > 0) else {
+                return (stage, moves_made == stage_start);
+            };
+            if moves_made < stage_start + moves {
+                return (stage, moves_made == stage_start);
+            }
+            stage_start += moves;
+            // the last stage starts over once its moves are done
+            if index + 1 < self.stages.len() {
+                index += 1;
+            }
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControlParseError(String);
+impl fmt::Display for TimeControlParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid time control: {}", self.0)
+    }
+}
+impl std::error::Error for TimeControlParseError {}
+/// Parses the PGN `TimeControl` tag format, in seconds: stages separated by `:`,
+/// each `moves/seconds` or `seconds`, with an optional `+increment` (Fischer),
+/// e.g. `40/5400+30:1800+30`. A `d` instead of the `+` gives a Bronstein delay.
+impl FromStr for TimeControl {
+    type Err = TimeControlParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || TimeControlParseError(s.to_string());
+        let seconds = |field: &str| {
+            field
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|_| error())
+        };
+        let mut stages = vec![];
+        for field in s.trim().split(':') {
+            let (moves, rest) = match field.split_once('/') {
+                Some((moves, rest)) => (Some(moves.parse::<u32>().map_err(|_| error())?), rest),
+                None => (None, field),
+            };
+            if moves == Some(0) {
+                return Err(error());
+            }
+            let (time, increment) = if let Some((time, increment)) = rest.split_once('+') {
+                (time, Increment::Fischer(seconds(increment)?))
+            } else if let Some((time, delay)) = rest.split_once('d') {
+                (time, Increment::Bronstein(seconds(delay)?))
+            } else {
+                (rest, Increment::None)
+            };
+            stages.push(TimeControlStage {
+                moves,
+                time: seconds(time)?,
+                increment,
+            });
+        }
+        Ok(TimeControl { stages })
+    }
+}
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", stage.time.as_secs())?;
+            match stage.increment {
+                Increment::None => (),
+                Increment::Fischer(increment) => write!(f, "+{}", increment.as_secs())?,
+                Increment::Bronstein(delay) => write!(f, "d{}", delay.as_secs())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The readings of a clock between two moves, to be put back with `Clock::restore`
+/// when moves are taken back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockState {
+    remaining: [Duration; 2],
+    moves_made: [u32; 2],
+}
+/// A two-player chess clock. Nothing runs until `start` is called; after that
+/// `press` ends the running side's move and starts the other side's clock.
+pub struct Clock<T: TimeSource> {
+    control: TimeControl,
+    source: T,
+    /// time left for white and black at the start of their current move
+    remaining: [Duration; 2],
+    moves_made: [u32; 2],
+    /// the side whose clock is running and when its move started
+    running: Option<(PieceColor, Duration)>,
+    flagged: Option<PieceColor>,
+}
+impl<T: TimeSource> Clock<T> {
+    pub fn new(control: TimeControl, source: T) -> Clock<T> {
+        let first_stage = control.stages[0].time;
+        Clock {
+            control,
+            source,
+            remaining: [first_stage; 2],
+            moves_made: [0; 2],
+            running: None,
+            flagged: None,
+        }
+    }
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+    pub fn running(&self) -> Option<PieceColor> {
+        self.running.map(|(color, _)| color)
+    }
+    pub fn is_started(&self) -> bool {
+        self.running.is_some()
+    }
+    /// runs `color`'s clock from now on. the side that was running keeps the time it
+    /// used, but gets no increment and is not counted as having moved
+    pub fn start(&mut self, color: PieceColor) {
+        if self.check_flag().is_some() {
+            return;
+        }
+        self.settle();
+        self.running = Some((color, self.source.now()));
+    }
+    /// stops both clocks, keeping the time used so far
+    pub fn stop(&mut self) {
+        if self.check_flag().is_none() {
+            self.settle();
+        }
+        self.running = None;
+    }
+    /// completes the running side's move: charges the time it took, adds the
+    /// increment, moves to the next stage if one was reached and starts the other
+    /// side's clock. does nothing if no clock is running or a flag has fallen
+    pub fn press(&mut self) {
+        if self.check_flag().is_some() {
+            return;
+        }
+        let Some((color, started)) = self.running else {
+            return;
+        };
+        let now = self.source.now();
+        let used = now.saturating_sub(started);
+        let side = color.index();
+        if used >= self.remaining[side] {
+            self.remaining[side] = Duration::ZERO;
+            self.flagged = Some(color);
+            self.running = None;
+            return;
+        }
+        let (stage, _) = self.control.stage_at(self.moves_made[side]);
+        let refund = match stage.increment {
+            Increment::None => Duration::ZERO,
+            Increment::Fischer(increment) => increment,
+            Increment::Bronstein(delay) => used.min(delay),
+        };
+        self.remaining[side] = self.remaining[side] - used + refund;
+        self.moves_made[side] += 1;
+        let (next_stage, starts_here) = self.control.stage_at(self.moves_made[side]);
+        if starts_here {
+            self.remaining[side] += next_stage.time;
+        }
+        self.running = Some((color.opposite(), now));
+    }
+    /// the time `color` has left right now, counting down while their clock runs
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let side = color.index();
+        match self.running {
+            Some((running, started)) if running == color => {
+                self.remaining[side].saturating_sub(self.source.now().saturating_sub(started))
+            }
+            _ => self.remaining[side],
+        }
+    }
+    /// the time left and moves made on both sides right now
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            remaining: [
+                self.remaining(PieceColor::White),
+                self.remaining(PieceColor::Black),
+            ],
+            moves_made: self.moves_made,
+        }
+    }
+    /// goes back to `state`, stopped and with no flag fallen
+    pub fn restore(&mut self, state: ClockState) {
+        self.remaining = state.remaining;
+        self.moves_made = state.moves_made;
+        self.running = None;
+        self.flagged = None;
+    }
+    /// the side whose flag has fallen, if any. once a flag falls the clock stops
+    pub fn check_flag(&mut self) -> Option<PieceColor> {
+        if self.flagged.is_none() {
+            if let Some((color, _)) = self.running {
+                if self.remaining(color).is_zero() {
+                    self.remaining[color.index()] = Duration::ZERO;
+                    self.flagged = Some(color);
+                    self.running = None;
+                }
+            }
+        }
+        self.flagged
+    }
+    /// charges the running side for the time used since its move started
+    fn settle(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.remaining[color.index()] = self.remaining(color);
+            self.running = Some((color, self.source.now()));
+        }
+    }
+}
+/// formats a clock reading as `h:mm:ss`, `m:ss`, or `s.t` in the last ten seconds
+pub fn format_clock_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else if seconds >= 10 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!("{}.{}", seconds, time.subsec_millis() / 100)
+    }
+}
@@ -0,0 +1,157 @@
+use crate::board::piece::{Piece, PieceColor};
+use crate::board::Board;
+use crate::consts::{COLS, ROWS};
+
+pub const PAWN_VALUE: i32 = 100;
+pub const KNIGHT_VALUE: i32 = 320;
+pub const BISHOP_VALUE: i32 = 330;
+pub const ROOK_VALUE: i32 = 500;
+pub const QUEEN_VALUE: i32 = 900;
+
+// Piece-square tables from white's point of view, laid out like the board:
+// the first row is the eighth rank. Black reads them mirrored.
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20,   0,   0,   0,   0, -20, -40,
+    -30,   0,  10,  15,  15,  10,   0, -30,
+    -30,   5,  15,  20,  20,  15,   5, -30,
+    -30,   0,  15,  20,  20,  15,   0, -30,
+    -30,   5,  10,  15,  15,  10,   5, -30,
+    -40, -20,   0,   5,   5,   0, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20, -10, -10, -10, -10, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   5,   5,  10,  10,   5,   5, -10,
+    -10,   0,  10,  10,  10,  10,   0, -10,
+    -10,  10,  10,  10,  10,  10,  10, -10,
+    -10,   5,   0,   0,   0,   0,   5, -10,
+    -20, -10, -10, -10, -10, -10, -10, -20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+     -5,   0,   5,   5,   5,   5,   0,  -5,
+      0,   0,   5,   5,   5,   5,   0,  -5,
+    -10,   5,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,   0,   0,   0,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -30, -40, -40, -50, -50, -40, -40, -30,
+    -20, -30, -30, -40, -40, -30, -30, -20,
+    -10, -20, -20, -20, -20, -20, -20, -10,
+     20,  20,   0,   0,   0,   0,  20,  20,
+     20,  30,  10,   0,   0,  10,  30,  20,
+];
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+/// non-pawn material of both sides at the start, where the middlegame king table applies fully
+const OPENING_PHASE: i32 = 2 * (2 * KNIGHT_VALUE + 2 * BISHOP_VALUE + 2 * ROOK_VALUE + QUEEN_VALUE);
+
+/// the material value of a piece, zero for kings and empty squares
+pub fn piece_value(piece: &Piece) -> i32 {
+    match piece {
+        Piece::Pawn(_) => PAWN_VALUE,
+        Piece::Knight(_) => KNIGHT_VALUE,
+        Piece::Bishop(_) => BISHOP_VALUE,
+        Piece::Rook(_) => ROOK_VALUE,
+        Piece::Queen(_) => QUEEN_VALUE,
+        Piece::King(_) | Piece::Empty(_) => 0,
+    }
+}
+/// material plus piece-square tables, in centipawns from the side to move's point
+/// of view. the king's table fades from the middlegame one to the endgame one as
+/// pieces come off
+pub fn evaluate(board: &Board) -> i32 {
+    let mut score = 0;
+    let mut phase = 0;
+    let mut king_squares = vec![];
+    for row in 0..ROWS {
+        for col in 0..COLS {
+            let piece = board.get_piece(row, col);
+            let Some(color) = piece.color() else {
+                continue;
+            };
+            let square = table_index(row, col, color);
+            let value = match piece {
+                Piece::Pawn(_) => PAWN_VALUE + PAWN_TABLE[square],
+                Piece::Knight(_) => KNIGHT_VALUE + KNIGHT_TABLE[square],
+                Piece::Bishop(_) => BISHOP_VALUE + BISHOP_TABLE[square],
+                Piece::Rook(_) => ROOK_VALUE + ROOK_TABLE[square],
+                Piece::Queen(_) => QUEEN_VALUE + QUEEN_TABLE[square],
+                Piece::King(_) => {
+                    king_squares.push((square, color));
+                    0
+                }
+                Piece::Empty(_) => 0,
+            };
+            if !matches!(piece, Piece::Pawn(_)) {
+                phase += piece_value(piece);
+            }
+            score += signed(value, color, board.turn_player);
+        }
+    }
+    let phase = phase.min(OPENING_PHASE);
+    for (square, color) in king_squares {
+        let value = (KING_MIDDLEGAME_TABLE[square] * phase
+            + KING_ENDGAME_TABLE[square] * (OPENING_PHASE - phase))
+            / OPENING_PHASE;
+        score += signed(value, color, board.turn_player);
+    }
+    score
+}
+fn table_index(row: usize, col: usize, color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => row * COLS + col,
+        PieceColor::Black => (ROWS - 1 - row) * COLS + col,
+    }
+}
+fn signed(value: i32, color: PieceColor, side_to_move: PieceColor) -> i32 {
+    if color == side_to_move {
+        value
+    } else {
+        -value
+    }
+}
@@ -0,0 +1,3 @@
+pub mod eval;
+pub mod search;
+pub use search::{search, Score, SearchInfo, SearchLimits, SearchResult};
@@ -0,0 +1,301 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::eval::{evaluate, piece_value, QUEEN_VALUE};
+use crate::board::moves::Move;
+use crate::board::piece::PromotionPiece;
+use crate::board::Board;
+
+/// the score of being mated right now. mates further away score less, by one per ply
+pub const MATE_SCORE: i32 = 30_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+/// the deepest the search ever goes, quiescence included
+const MAX_PLY: usize = 64;
+/// how many nodes go by between looks at the stop flag and the clock
+const STOP_CHECK_INTERVAL: u64 = 1024;
+
+/// When to stop searching. A search with no limits runs until it is stopped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub move_time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+    pub fn move_time(move_time: Duration) -> SearchLimits {
+        SearchLimits {
+            move_time: Some(move_time),
+            ..SearchLimits::default()
+        }
+    }
+}
+/// A search score from the side to move's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// mate in this many moves, negative when the side to move is getting mated
+    Mate(i32),
+}
+impl Score {
+    fn from_search(score: i32) -> Score {
+        if score.abs() < MATE_SCORE - MAX_PLY as i32 {
+            return Score::Centipawns(score);
+        }
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        Score::Mate(if score > 0 { moves } else { -moves })
+    }
+}
+/// What the search knows after finishing one depth of iterative deepening.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    /// the expected line, starting with the best move
+    pub pv: Vec<Move>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal moves
+    pub best_move: Option<Move>,
+    pub score: Score,
+    /// the last depth that was searched to the end
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+/// finds the best move for the side to move with iterative deepening until `limits`
+/// are reached or `stop` is set, calling `on_info` after every completed depth.
+/// a depth cut short is thrown away, except that some move is always returned
+/// when there is one
+pub fn search<F: FnMut(&SearchInfo)>(
+    board: &Board,
+    limits: &SearchLimits,
+    stop: &AtomicBool,
+    mut on_info: F,
+) -> SearchResult {
+    let start = Instant::now();
+    let mut searcher = Searcher {
+        stop,
+        deadline: limits.move_time.map(|time| start + time),
+        node_limit: limits.nodes,
+        nodes: 0,
+        stopped: false,
+        killers: std::array::from_fn(|_| [None, None]),
+    };
+    let mut board = board.clone();
+    let mut result = SearchResult {
+        best_move: board.legal_moves().into_iter().next(),
+        score: Score::Centipawns(0),
+        depth: 0,
+        nodes: 0,
+        pv: vec![],
+    };
+    let max_depth = limits
+        .depth
+        .unwrap_or(MAX_PLY as u32)
+        .clamp(1, MAX_PLY as u32);
+    for depth in 1..=max_depth {
+        let mut pv = vec![];
+        let score = searcher.negamax(
+            &mut board, depth, 0, -INFINITY, INFINITY, &result.pv, &mut pv,
+        );
+        if searcher.stopped {
+            break;
+        }
+        let info = SearchInfo {
+            depth,
+            score: Score::from_search(score),
+            nodes: searcher.nodes,
+            time: start.elapsed(),
+            pv,
+        };
+        on_info(&info);
+        result = SearchResult {
+            best_move: info.pv.first().cloned().or(result.best_move),
+            score: info.score,
+            depth,
+            nodes: info.nodes,
+            pv: info.pv,
+        };
+        // a forced mate will not get any better, and a deeper search would not
+        // finish in the time that is left
+        if matches!(result.score, Score::Mate(_)) {
+            break;
+        }
+        if let Some(move_time) = limits.move_time {
+            if start.elapsed() > move_time / 2 {
+                break;
+            }
+        }
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+struct Searcher<'a> {
+    stop: &'a AtomicBool,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    nodes: u64,
+    /// set once any limit is hit, after which every score is meaningless
+    stopped: bool,
+    /// two quiet moves per ply that caused a beta cutoff in a sibling node
+    killers: [[Option<Move>; 2]; MAX_PLY],
+}
+impl Searcher<'_> {
+    /// negamax with alpha-beta pruning. `pv_hint` is the line from the previous
+    /// iteration, searched first while this node lies on it. fills `pv` with the best line
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv_hint: &[Move],
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        pv.clear();
+        if self.should_stop() {
+            return 0;
+        }
+        if ply > 0 && (board.halfmove_clock() >= 100 || board.is_repetition()) {
+            return 0;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(board, alpha, beta, ply);
+        }
+        self.nodes += 1;
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if board.is_in_check() {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        self.order_moves(&mut moves, ply, pv_hint.first());
+        let mut child_pv = vec![];
+        for mv in moves {
+            let quiet = !mv.is_capture() && mv.promotion.is_none();
+            let child_hint = match pv_hint.split_first() {
+                Some((hint, rest)) if *hint == mv => rest,
+                _ => &[],
+            };
+            board.make_move(mv.clone());
+            let score = -self.negamax(
+                board,
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                child_hint,
+                &mut child_pv,
+            );
+            board.undo_move();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                if quiet {
+                    self.store_killer(ply, mv);
+                }
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut child_pv);
+            }
+        }
+        alpha
+    }
+    /// searches captures and promotions only, so positions are scored once they are quiet
+    fn quiescence(&mut self, board: &mut Board, mut alpha: i32, beta: i32, ply: usize) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+        if ply >= MAX_PLY {
+            return alpha;
+        }
+        let mut moves: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_capture() || mv.promotion == Some(PromotionPiece::Queen))
+            .collect();
+        moves.sort_by_key(|mv| -mvv_lva(mv));
+        for mv in moves {
+            board.make_move(mv);
+            let score = -self.quiescence(board, -beta, -alpha, ply + 1);
+            board.undo_move();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+    /// the previous iteration's move first, then captures by MVV-LVA, then killers
+    fn order_moves(&self, moves: &mut [Move], ply: usize, hint: Option<&Move>) {
+        moves.sort_by_cached_key(|mv| {
+            let priority = if Some(mv) == hint {
+                1_000_000
+            } else if mv.is_capture() || mv.promotion.is_some() {
+                100_000 + mvv_lva(mv)
+            } else if self.killers[ply][0].as_ref() == Some(mv) {
+                90_000
+            } else if self.killers[ply][1].as_ref() == Some(mv) {
+                80_000
+            } else {
+                0
+            };
+            -priority
+        });
+    }
+    fn store_killer(&mut self, ply: usize, mv: Move) {
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(&mv) {
+            killers[1] = killers[0].take();
+            killers[0] = Some(mv);
+        }
+    }
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(STOP_CHECK_INTERVAL) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                || self.node_limit.is_some_and(|limit| self.nodes >= limit);
+        }
+        self.stopped
+    }
+}
+/// most valuable victim, least valuable attacker: big captures by small pieces first
+fn mvv_lva(mv: &Move) -> i32 {
+    let victim = mv.captured.as_ref().map_or(0, piece_value);
+    let promotion = match mv.promotion {
+        Some(PromotionPiece::Queen) => QUEEN_VALUE,
+        _ => 0,
+    };
+    10 * (victim + promotion) - piece_value(&mv.piece)
+}
@@ -1,11 +1,40 @@
 pub mod board;
+pub mod book;
+pub mod clock;
+pub mod engine;
 pub mod consts {
-    pub const GTK_NONE: Option<&gtk::Widget> = None;
     pub const HEIGHT: i32 = 700;
     pub const WIDTH: i32 = 700;
     pub const ROWS: usize = 8;
     pub const COLS: usize = 8;
-    pub const WHITE_CSS: &str = "button { background-color: #eeeed2; }";
-    pub const BLACK_CSS: &str = "button { background-color: #769656; }";
+    pub const LIGHT_SQUARE_COLOR: &str = "#eeeed2";
+    pub const DARK_SQUARE_COLOR: &str = "#769656";
+    /// laid over the square of the piece the player picked up
+    pub const SELECTED_SQUARE_COLOR: &str = "rgba(20, 85, 30, 0.5)";
+    /// laid over the squares the last move went from and to
+    pub const LAST_MOVE_COLOR: &str = "rgba(246, 246, 105, 0.6)";
+    /// the dots and rings on the squares the picked up piece can go to
+    pub const MOVE_HINT_COLOR: &str = "rgba(20, 85, 30, 0.5)";
+    /// the glow under a king in check
+    pub const CHECK_COLOR: &str = "rgba(255, 0, 0, 0.9)";
+    pub const CLOCK_CSS: &str =
+        "label.clock { font-family: monospace; font-size: 20pt; padding: 2px 8px; }
+        label.clock-running { background-color: #f6f669; }";
+    /// the clock until another is picked in the preferences, in the PGN
+    /// TimeControl tag format, see `clock::TimeControl`
+    pub const DEFAULT_TIME_CONTROL: &str = "300+3";
+    /// how long the computer thinks about a move, when its clock allows
+    pub const ENGINE_MOVE_TIME_MS: u64 = 2000;
+    /// the external UCI engines, relative to the user's config directory
+    pub const ENGINES_CONFIG: &str = "xp-chess/engines.ini";
+    pub const ENGINE_HANDSHAKE_TIMEOUT_MS: u64 = 5000;
+    /// the settings kept between sessions, relative to the user's config directory
+    pub const PREFERENCES: &str = "xp-chess/preferences.ini";
+    /// the Polyglot opening book the computer and the opening explorer use,
+    /// relative to the user's config directory
+    pub const OPENING_BOOK: &str = "xp-chess/book.bin";
+    pub const CURRENT_MOVE_CSS: &str = "button.current-move { background-color: #f6f669; }";
 }
+pub mod pgn;
+pub mod uci;
 pub mod ui;
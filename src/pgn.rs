@@ -0,0 +1,656 @@
+//! Reading and writing games in Portable Game Notation.
+//!
+//! Movetext is replayed through `Board::try_move_piece` while it is read, so a
+//! parsed `PgnGame` only ever holds legal moves.
+use std::fmt;
+
+use crate::board::fen::FenError;
+use crate::board::piece::{PieceColor, PromotionPiece};
+use crate::board::Board;
+
+/// The tags every PGN game carries, in the order they are written.
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// One game: its tag pairs, the main line and the result token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnGame {
+    /// tag pairs in the order they were read or added
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    /// "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+}
+/// A move in the main line or in a variation, with everything annotated on it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub san: String,
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub promotion: Option<PromotionPiece>,
+    /// numeric annotation glyphs, `!` is 1, `?` is 2 and so on
+    pub nags: Vec<u8>,
+    /// comment written before the move. a `}` in it is written out as `)`
+    pub comment_before: Option<String>,
+    /// comment written after the move, written out the same way
+    pub comment: Option<String>,
+    /// alternatives to this move, each starting from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+impl PgnMove {
+    fn new(
+        san: String,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: Option<PromotionPiece>,
+    ) -> PgnMove {
+        PgnMove {
+            san,
+            from,
+            to,
+            promotion,
+            nags: vec![],
+            comment_before: None,
+            comment: None,
+            variations: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    /// the text is not well-formed PGN
+    Syntax { line: usize, message: String },
+    /// the FEN tag of a game could not be read
+    InvalidFen { game: usize, error: FenError },
+    /// a move in the movetext is illegal or ambiguous in its position
+    IllegalMove {
+        game: usize,
+        line: usize,
+        move_number: u32,
+        color: PieceColor,
+        san: String,
+    },
+    /// a move of a game built or edited by hand cannot be played in its position.
+    /// `ply` counts the main line from 1
+    UnplayableMove { ply: usize, san: String },
+}
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            PgnError::InvalidFen { game, error } => {
+                write!(f, "game {}: invalid FEN tag: {}", game, error)
+            }
+            PgnError::IllegalMove {
+                game,
+                line,
+                move_number,
+                color,
+                san,
+            } => {
+                let dots = match color {
+                    PieceColor::White => ".",
+                    PieceColor::Black => "...",
+                };
+                write!(
+                    f,
+                    "game {}, line {}: illegal move {}{} {}",
+                    game, line, move_number, dots, san
+                )
+            }
+            PgnError::UnplayableMove { ply, san } => {
+                write!(f, "move {} of the game, {}, cannot be played", ply, san)
+            }
+        }
+    }
+}
+impl std::error::Error for PgnError {}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        let mut tags: Vec<(String, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| (name.to_string(), "?".to_string()))
+            .collect();
+        tags[2].1 = "????.??.??".to_string();
+        tags[6].1 = "*".to_string();
+        PgnGame {
+            tags,
+            moves: vec![],
+            result: "*".to_string(),
+        }
+    }
+}
+impl PgnGame {
+    /// an empty game that starts from `board` instead of the standard position
+    pub fn from_position(board: &Board) -> PgnGame {
+        let mut game = PgnGame::default();
+        let fen = board.to_fen();
+        if fen != crate::board::fen::STARTING_FEN {
+            game.set_tag("SetUp", "1");
+            game.set_tag("FEN", &fen);
+        }
+        game
+    }
+    /// a game holding every move played on `board`, from the position before the first
+    pub fn from_board(board: &Board) -> PgnGame {
+        let mut start = board.clone();
+        while start.undo_move().is_some() {}
+        let mut game = PgnGame::from_position(&start);
+        for mv in board.history() {
+            let san = start.move_to_san(mv.from, mv.to, mv.promotion);
+            game.moves
+                .push(PgnMove::new(san, mv.from, mv.to, mv.promotion));
+            start.make_move(mv.clone());
+        }
+        game
+    }
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    /// sets a tag, replacing its value if it is already there
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+    /// sets the result token and the matching Result tag
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        self.set_tag("Result", result);
+    }
+    /// the position the game starts from, taken from the FEN tag if present
+    pub fn starting_board(&self) -> Result<Board, FenError> {
+        match self.tag("FEN") {
+            Some(fen) => Board::from_fen(fen),
+            None => Ok(Board::default()),
+        }
+    }
+    /// the position after the last move of the main line. only fails for games
+    /// whose tags or moves were set by hand
+    pub fn final_board(&self) -> Result<Board, PgnError> {
+        let mut board = self
+            .starting_board()
+            .map_err(|error| PgnError::InvalidFen { game: 1, error })?;
+        for (ply, mv) in self.moves.iter().enumerate() {
+            if board.try_move_piece(mv.from, mv.to, mv.promotion).is_err() {
+                return Err(PgnError::UnplayableMove {
+                    ply: ply + 1,
+                    san: mv.san.clone(),
+                });
+            }
+        }
+        Ok(board)
+    }
+    /// plays a move at the end of the main line. returns false if it is not legal
+    /// there, or if the main line itself cannot be played
+    pub fn push_move(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        promotion: Option<PromotionPiece>,
+    ) -> bool {
+        let Ok(mut board) = self.final_board() else {
+            return false;
+        };
+        if !board.get_legal_moves(from).contains(&to) {
+            return false;
+        }
+        let san = board.move_to_san(from, to, promotion);
+        self.moves.push(PgnMove::new(san, from, to, promotion));
+        true
+    }
+    /// writes the game as PGN, seven-tag roster first and movetext wrapped at 80
+    /// columns. PGN cannot escape a `}` in a comment, so it is written as `)`.
+    /// fails if the FEN tag cannot be read, as the moves cannot be numbered then
+    pub fn to_pgn(&self) -> Result<String, FenError> {
+        let board = self.starting_board()?;
+        let mut out = String::new();
+        for name in SEVEN_TAG_ROSTER {
+            let value = match (name, self.tag(name)) {
+                ("Result", _) => self.result.as_str(),
+                (_, Some(value)) => value,
+                ("Date", None) => "????.??.??",
+                (_, None) => "?",
+            };
+            out.push_str(&format_tag(name, value));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) {
+                out.push_str(&format_tag(name, value));
+            }
+        }
+        out.push('\n');
+
+        let mut tokens = vec![];
+        write_line(
+            &self.moves,
+            board.fullmove_number(),
+            board.turn_player,
+            &mut tokens,
+        );
+        tokens.push(self.result.clone());
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > 80 {
+                out.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                out.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            out.push_str(&token);
+        }
+        out.push_str("\n\n");
+        Ok(out)
+    }
+}
+fn format_tag(name: &str, value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]\n", name, escaped)
+}
+/// a brace comment. PGN has no escape for `}` inside one, so it becomes `)`
+fn format_comment(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+fn write_line(
+    moves: &[PgnMove],
+    mut move_number: u32,
+    mut color: PieceColor,
+    tokens: &mut Vec<String>,
+) {
+    // black moves only get a number at the start of a line or after an interruption
+    let mut needs_number = true;
+    for mv in moves {
+        if let Some(comment) = &mv.comment_before {
+            tokens.push(format_comment(comment));
+            needs_number = true;
+        }
+        match color {
+            PieceColor::White => tokens.push(format!("{}.", move_number)),
+            PieceColor::Black if needs_number => tokens.push(format!("{}...", move_number)),
+            PieceColor::Black => (),
+        }
+        tokens.push(mv.san.clone());
+        needs_number = false;
+        for nag in &mv.nags {
+            tokens.push(format!("${}", nag));
+        }
+        if let Some(comment) = &mv.comment {
+            tokens.push(format_comment(comment));
+            needs_number = true;
+        }
+        for variation in &mv.variations {
+            // parentheses hug the first and last tokens of the variation
+            let start = tokens.len();
+            write_line(variation, move_number, color, tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            }
+            needs_number = true;
+        }
+        if color == PieceColor::Black {
+            move_number += 1;
+        }
+        color = color.opposite();
+    }
+}
+
+/// reads every game in a PGN file
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        game_number: 0,
+    };
+    let mut games = vec![];
+    while parser.peek().is_some() {
+        games.push(parser.parse_game()?);
+    }
+    Ok(games)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    /// a move number such as "12." or "12..." - only used for checking nothing
+    MoveNumber,
+    San(String),
+}
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, PgnError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    let mut line = 1;
+    let syntax = |line: usize, message: &str| PgnError::Syntax {
+        line,
+        message: message.to_string(),
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let at_line_start = i == 0 || chars[i - 1] == '\n';
+        match c {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            // escape mechanism: the whole line is ignored
+            '%' if at_line_start => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ';' => {
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                let comment: String = chars[start..i].iter().collect();
+                tokens.push((Token::Comment(comment.trim().to_string()), line));
+            }
+            '{' => {
+                let start_line = line;
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '}' {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(syntax(start_line, "unterminated comment"));
+                }
+                let comment: String = chars[start..i].iter().collect();
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                tokens.push((Token::Comment(comment), start_line));
+                i += 1;
+            }
+            '[' => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p);
+                let (tag, consumed) =
+                    parse_tag(&chars[i..end]).ok_or_else(|| syntax(line, "malformed tag pair"))?;
+                tokens.push((tag, line));
+                i += consumed;
+            }
+            '(' => {
+                tokens.push((Token::VariationStart, line));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((Token::VariationEnd, line));
+                i += 1;
+            }
+            '$' => {
+                let start = i + 1;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let nag: String = chars[start..i].iter().collect();
+                let nag = nag.parse().map_err(|_| syntax(line, "invalid NAG"))?;
+                tokens.push((Token::Nag(nag), line));
+            }
+            '!' | '?' => {
+                let start = i;
+                while i < chars.len() && matches!(chars[i], '!' | '?') {
+                    i += 1;
+                }
+                let suffix: String = chars[start..i].iter().collect();
+                let nag = match suffix.as_str() {
+                    "!" => 1,
+                    "?" => 2,
+                    "!!" => 3,
+                    "??" => 4,
+                    "!?" => 5,
+                    "?!" => 6,
+                    _ => return Err(syntax(line, "unknown move annotation")),
+                };
+                tokens.push((Token::Nag(nag), line));
+            }
+            '*' => {
+                tokens.push((Token::Result("*".to_string()), line));
+                i += 1;
+            }
+            c if c.is_ascii_alphanumeric() => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || "_+#=:-/".contains(chars[i]))
+                {
+                    i += 1;
+                }
+                let symbol: String = chars[start..i].iter().collect();
+                if matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2") {
+                    tokens.push((Token::Result(symbol), line));
+                } else if symbol.chars().all(|c| c.is_ascii_digit()) {
+                    while i < chars.len() && chars[i] == '.' {
+                        i += 1;
+                    }
+                    tokens.push((Token::MoveNumber, line));
+                } else {
+                    tokens.push((Token::San(symbol), line));
+                }
+            }
+            '.' => i += 1,
+            _ => return Err(syntax(line, &format!("unexpected character '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+/// reads `[Name "value"]` at the start of `chars`, returning the tag and how many chars it used
+fn parse_tag(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 1;
+    while chars.get(i)?.is_whitespace() {
+        i += 1;
+    }
+    let name_start = i;
+    while chars.get(i)?.is_ascii_alphanumeric() || chars[i] == '_' {
+        i += 1;
+    }
+    let name: String = chars[name_start..i].iter().collect();
+    while chars.get(i)?.is_whitespace() {
+        i += 1;
+    }
+    if *chars.get(i)? != '"' || name.is_empty() {
+        return None;
+    }
+    i += 1;
+    let mut value = String::new();
+    loop {
+        match *chars.get(i)? {
+            '\\' => {
+                value.push(*chars.get(i + 1)?);
+                i += 2;
+            }
+            '"' => break,
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    i += 1;
+    while chars.get(i)?.is_whitespace() {
+        i += 1;
+    }
+    if *chars.get(i)? != ']' {
+        return None;
+    }
+    Some((Token::Tag(name, value), i + 1))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    game_number: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+    fn parse_game(&mut self) -> Result<PgnGame, PgnError> {
+        self.game_number += 1;
+        let mut game = PgnGame {
+            tags: vec![],
+            moves: vec![],
+            result: "*".to_string(),
+        };
+        while let Some(Token::Tag(name, value)) = self.peek() {
+            let (name, value) = (name.clone(), value.clone());
+            game.set_tag(&name, &value);
+            self.pos += 1;
+        }
+        let board = game
+            .starting_board()
+            .map_err(|error| PgnError::InvalidFen {
+                game: self.game_number,
+                error,
+            })?;
+        game.moves = self.parse_line(board, 0)?;
+        match self.peek() {
+            Some(Token::Result(result)) => {
+                game.result = result.clone();
+                self.pos += 1;
+            }
+            // a game without a termination marker ends where the next one's tags begin
+            Some(Token::Tag(..)) | None => {
+                game.result = game.tag("Result").unwrap_or("*").to_string();
+            }
+            Some(_) => {
+                return Err(PgnError::Syntax {
+                    line: self.line(),
+                    message: "unexpected ')'".to_string(),
+                })
+            }
+        }
+        if game.tag("Result").is_none() {
+            game.set_tag("Result", &game.result.clone());
+        }
+        Ok(game)
+    }
+    /// reads moves from `board` until the line ends: a result, a closing
+    /// parenthesis, the next game's tags or the end of the input
+    fn parse_line(&mut self, mut board: Board, depth: usize) -> Result<Vec<PgnMove>, PgnError> {
+        let mut moves: Vec<PgnMove> = vec![];
+        let mut pending_comment: Option<String> = None;
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Tag(..) | Token::Result(_) => break,
+                Token::VariationEnd => {
+                    if depth == 0 {
+                        return Err(PgnError::Syntax {
+                            line: self.line(),
+                            message: "')' without a matching '('".to_string(),
+                        });
+                    }
+                    break;
+                }
+                Token::MoveNumber => self.pos += 1,
+                Token::Comment(comment) => {
+                    self.pos += 1;
+                    match moves.last_mut() {
+                        Some(last) if pending_comment.is_none() => {
+                            append_comment(&mut last.comment, comment)
+                        }
+                        _ => append_comment(&mut pending_comment, comment),
+                    }
+                }
+                Token::Nag(nag) => {
+                    self.pos += 1;
+                    match moves.last_mut() {
+                        Some(last) => last.nags.push(nag),
+                        None => {
+                            return Err(PgnError::Syntax {
+                                line: self.line(),
+                                message: "annotation before any move".to_string(),
+                            })
+                        }
+                    }
+                }
+                Token::VariationStart => {
+                    self.pos += 1;
+                    let line = self.line();
+                    // a variation replaces the last move, so it starts from before it
+                    let mut before_last = board.clone();
+                    if !moves.is_empty() {
+                        before_last.undo_move();
+                    }
+                    let variation = self.parse_line(before_last, depth + 1)?;
+                    if self.peek() != Some(&Token::VariationEnd) {
+                        return Err(PgnError::Syntax {
+                            line,
+                            message: "unterminated variation".to_string(),
+                        });
+                    }
+                    self.pos += 1;
+                    match moves.last_mut() {
+                        Some(last) => last.variations.push(variation),
+                        None => {
+                            return Err(PgnError::Syntax {
+                                line,
+                                message: "variation before any move".to_string(),
+                            })
+                        }
+                    }
+                }
+                Token::San(san) => {
+                    let illegal = PgnError::IllegalMove {
+                        game: self.game_number,
+                        line: self.line(),
+                        move_number: board.fullmove_number(),
+                        color: board.turn_player,
+                        san: san.clone(),
+                    };
+                    self.pos += 1;
+                    let Ok(parsed) = board.parse_san(&san) else {
+                        return Err(illegal);
+                    };
+                    let (from, to, promotion) = (parsed.from, parsed.to, parsed.promotion);
+                    let mut mv =
+                        PgnMove::new(board.move_to_san(from, to, promotion), from, to, promotion);
+                    mv.comment_before = pending_comment.take();
+                    if board.try_move_piece(from, to, promotion).is_err() {
+                        return Err(illegal);
+                    }
+                    moves.push(mv);
+                }
+            }
+        }
+        // a comment with no move after it belongs to the last move
+        if let (Some(comment), Some(last)) = (pending_comment, moves.last_mut()) {
+            append_comment(&mut last.comment, comment);
+        }
+        Ok(moves)
+    }
+}
+fn append_comment(slot: &mut Option<String>, comment: String) {
+    match slot {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&comment);
+        }
+        None => *slot = Some(comment),
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::Board;
+use crate::engine::{Score, SearchInfo};
+
+/// How to start an external UCI engine and what to set up once it is running.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EngineConfig {
+    /// shown to the user until the engine reports its own name
+    pub name: String,
+    pub path: PathBuf,
+    pub args: Vec<String>,
+    /// sent with `setoption` after the handshake, in order
+    pub options: Vec<(String, String)>,
+}
+
+/// One line from an engine, parsed as far as a GUI cares about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UciMessage {
+    Id {
+        name: String,
+        value: String,
+    },
+    UciOk,
+    ReadyOk,
+    Info(UciInfo),
+    /// `best_move` is in UCI notation, "0000" when there is no legal move
+    BestMove {
+        best_move: String,
+        ponder: Option<String>,
+    },
+    /// an `option` declaration, kept as the text after "option "
+    Option(String),
+    Other(String),
+}
+impl UciMessage {
+    pub fn parse(line: &str) -> UciMessage {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("id") => match tokens.next() {
+                Some(name) => UciMessage::Id {
+                    name: name.to_string(),
+                    value: tokens.collect::<Vec<_>>().join(" "),
+                },
+                None => UciMessage::Other(line.to_string()),
+            },
+            Some("uciok") => UciMessage::UciOk,
+            Some("readyok") => UciMessage::ReadyOk,
+            Some("info") => UciMessage::Info(UciInfo::parse(tokens.collect())),
+            Some("bestmove") => match tokens.next() {
+                Some(best_move) => UciMessage::BestMove {
+                    best_move: best_move.to_string(),
+                    ponder: match (tokens.next(), tokens.next()) {
+                        (Some("ponder"), Some(ponder)) => Some(ponder.to_string()),
+                        _ => None,
+                    },
+                },
+                None => UciMessage::Other(line.to_string()),
+            },
+            Some("option") => UciMessage::Option(
+                line.trim_start()
+                    .trim_start_matches("option")
+                    .trim()
+                    .to_string(),
+            ),
+            _ => UciMessage::Other(line.to_string()),
+        }
+    }
+}
+
+/// The fields of an `info` line. Moves in `pv` are in UCI notation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UciInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    /// from the point of view of the side to move
+    pub score: Option<Score>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    pub pv: Vec<String>,
+    pub string: Option<String>,
+}
+impl UciInfo {
+    /// parses the tokens after "info". fields this does not know are skipped
+    pub fn parse(tokens: Vec<&str>) -> UciInfo {
+        let mut info = UciInfo::default();
+        let mut i = 0;
+        let number = |i: usize| tokens.get(i + 1).and_then(|t| t.parse::<i64>().ok());
+        while i < tokens.len() {
+            match tokens[i] {
+                "depth" => info.depth = number(i).map(|n| n as u32),
+                "seldepth" => info.seldepth = number(i).map(|n| n as u32),
+                "multipv" => info.multipv = number(i).map(|n| n as u32),
+                "nodes" => info.nodes = number(i).map(|n| n as u64),
+                "nps" => info.nps = number(i).map(|n| n as u64),
+                "time" => info.time = number(i).map(|n| Duration::from_millis(n as u64)),
+                "score" => {
+                    info.score = match (tokens.get(i + 1), number(i + 1)) {
+                        (Some(&"cp"), Some(cp)) => Some(Score::Centipawns(cp as i32)),
+                        (Some(&"mate"), Some(moves)) => Some(Score::Mate(moves as i32)),
+                        _ => None,
+                    };
+                    // skip the unit, lowerbound and upperbound are left to the catch-all
+                    i += 1;
+                }
+                // pv and string run to the end of the line
+                "pv" => {
+                    info.pv = tokens[i + 1..].iter().map(|t| t.to_string()).collect();
+                    break;
+                }
+                "string" => {
+                    info.string = Some(tokens[i + 1..].join(" "));
+                    break;
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            i += 2;
+        }
+        info
+    }
+}
+impl From<&SearchInfo> for UciInfo {
+    fn from(info: &SearchInfo) -> Self {
+        let millis = info.time.as_millis().max(1) as u64;
+        UciInfo {
+            depth: Some(info.depth),
+            score: Some(info.score),
+            nodes: Some(info.nodes),
+            nps: Some(info.nodes * 1000 / millis),
+            time: Some(info.time),
+            pv: info.pv.iter().map(|mv| mv.to_uci()).collect(),
+            ..UciInfo::default()
+        }
+    }
+}
+/// writes the whole `info` line
+impl fmt::Display for UciInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "info")?;
+        let fields = [
+            ("depth", self.depth.map(u64::from)),
+            ("seldepth", self.seldepth.map(u64::from)),
+            ("multipv", self.multipv.map(u64::from)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value)?;
+            }
+        }
+        match self.score {
+            Some(Score::Centipawns(cp)) => write!(f, " score cp {}", cp)?,
+            Some(Score::Mate(moves)) => write!(f, " score mate {}", moves)?,
+            None => (),
+        }
+        let fields = [
+            ("nodes", self.nodes),
+            ("nps", self.nps),
+            ("time", self.time.map(|time| time.as_millis() as u64)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value)?;
+            }
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv {}", self.pv.join(" "))?;
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {}", string)?;
+        }
+        Ok(())
+    }
+}
+
+/// The parameters of a `go` command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GoCommand {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<Duration>,
+    pub white_time: Option<Duration>,
+    pub black_time: Option<Duration>,
+    pub white_increment: Option<Duration>,
+    pub black_increment: Option<Duration>,
+    pub moves_to_go: Option<u32>,
+    pub infinite: bool,
+}
+impl GoCommand {
+    /// parses the tokens after "go". parameters this does not know are skipped
+    pub fn parse(tokens: Vec<&str>) -> GoCommand {
+        let mut go = GoCommand::default();
+        let mut tokens = tokens.into_iter();
+        while let Some(token) = tokens.next() {
+            let mut number = || tokens.next().and_then(|t| t.parse::<u64>().ok());
+            let millis = |ms: Option<u64>| ms.map(Duration::from_millis);
+            match token {
+                "depth" => go.depth = number().map(|d| d as u32),
+                "nodes" => go.nodes = number(),
+                "movetime" => go.move_time = millis(number()),
+                "wtime" => go.white_time = millis(number()),
+                "btime" => go.black_time = millis(number()),
+                "winc" => go.white_increment = millis(number()),
+                "binc" => go.black_increment = millis(number()),
+                "movestogo" => go.moves_to_go = number().map(|m| m as u32),
+                "infinite" => go.infinite = true,
+                _ => (),
+            }
+        }
+        go
+    }
+}
+/// writes the whole `go` command
+impl fmt::Display for GoCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "go")?;
+        let millis = |time: Option<Duration>| time.map(|time| time.as_millis() as u64);
+        let fields = [
+            ("depth", self.depth.map(u64::from)),
+            ("nodes", self.nodes),
+            ("movetime", millis(self.move_time)),
+            ("wtime", millis(self.white_time)),
+            ("btime", millis(self.black_time)),
+            ("winc", millis(self.white_increment)),
+            ("binc", millis(self.black_increment)),
+            ("movestogo", self.moves_to_go.map(u64::from)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value)?;
+            }
+        }
+        if self.infinite {
+            write!(f, " infinite")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum UciError {
+    Io(io::Error),
+    /// the engine did not answer `uci` or `isready` in time
+    Timeout(&'static str),
+    /// the engine closed its output
+    Exited,
+}
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::Io(e) => write!(f, "{}", e),
+            UciError::Timeout(waiting_for) => write!(f, "timed out waiting for {}", waiting_for),
+            UciError::Exited => write!(f, "the engine exited"),
+        }
+    }
+}
+impl std::error::Error for UciError {}
+impl From<io::Error> for UciError {
+    fn from(e: io::Error) -> Self {
+        UciError::Io(e)
+    }
+}
+
+/// A running external engine. Its output is read on a thread of its own and
+/// queued, so nothing here blocks except `start`.
+pub struct UciEngine {
+    name: String,
+    /// taken by `drop`, which hands it to a thread that sees it exit
+    child: Option<Child>,
+    stdin: ChildStdin,
+    messages: Receiver<UciMessage>,
+}
+impl UciEngine {
+    /// starts the engine, does the `uci` handshake, sets the configured options
+    /// and waits until the engine is ready
+    pub fn start(config: &EngineConfig, timeout: Duration) -> Result<UciEngine, UciError> {
+        let mut child = Command::new(&config.path)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(UciMessage::parse(&line)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = UciEngine {
+            name: config.name.clone(),
+            child: Some(child),
+            stdin,
+            messages,
+        };
+        let deadline = Instant::now() + timeout;
+        engine.send("uci")?;
+        loop {
+            match engine.recv_until(deadline, "uciok")? {
+                UciMessage::Id { name, value } if name == "name" => engine.name = value,
+                UciMessage::UciOk => break,
+                _ => (),
+            }
+        }
+        for (name, value) in &config.options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.send("isready")?;
+        while engine.recv_until(deadline, "readyok")? != UciMessage::ReadyOk {}
+        Ok(engine)
+    }
+    /// the name the engine gave in `id name`, or the configured one
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn send(&mut self, command: &str) -> Result<(), UciError> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+    pub fn new_game(&mut self) -> Result<(), UciError> {
+        self.send("ucinewgame")
+    }
+    /// sends the board as its starting position plus the moves played since, so the
+    /// engine knows about repetitions
+    pub fn set_position(&mut self, board: &Board) -> Result<(), UciError> {
+        let mut start = board.clone();
+        while start.undo_move().is_some() {}
+        let mut command = format!("position fen {}", start.to_fen());
+        if !board.history().is_empty() {
+            command.push_str(" moves");
+            for mv in board.history() {
+                command.push(' ');
+                command.push_str(&mv.to_uci());
+            }
+        }
+        self.send(&command)
+    }
+    pub fn go(&mut self, go: &GoCommand) -> Result<(), UciError> {
+        self.send(&go.to_string())
+    }
+    /// asks the engine to stop searching. it still answers with a `bestmove`
+    pub fn stop(&mut self) -> Result<(), UciError> {
+        self.send("stop")
+    }
+    /// the next message, if one has arrived. `Err(UciError::Exited)` once the engine
+    /// has closed its output and everything before that was read
+    pub fn try_recv(&self) -> Result<Option<UciMessage>, UciError> {
+        match self.messages.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(UciError::Exited),
+        }
+    }
+    /// waits up to `timeout` for the next message
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<UciMessage>, UciError> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(message) => Ok(Some(message)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(UciError::Exited),
+        }
+    }
+    fn recv_until(
+        &self,
+        deadline: Instant,
+        waiting_for: &'static str,
+    ) -> Result<UciMessage, UciError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.recv_timeout(timeout)?
+            .ok_or(UciError::Timeout(waiting_for))
+    }
+}
+/// asks the engine to quit, and kills it if it has not after a moment. the
+/// waiting happens on a thread of its own so dropping never blocks the caller
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+        thread::spawn(move || {
+            for _ in 0..20 {
+                if let Ok(Some(_)) = child.try_wait() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        });
+    }
+}
@@ -1,13 +1,34 @@
-use crate::board::piece::Piece;
-use crate::board::{Board, MoveType};
-use crate::consts::{BLACK_CSS, COLS, GTK_NONE, HEIGHT, ROWS, WHITE_CSS, WIDTH};
+use crate::board::moves::Move;
+use crate::board::piece::{PieceColor, PromotionPiece};
+use crate::board::status::{GameResult, GameStatus};
+use crate::board::Board;
+use crate::book::Book;
+use crate::clock::{Clock, ClockState, SystemTime};
+use crate::consts::{ENGINE_MOVE_TIME_MS, HEIGHT, WIDTH};
+use crate::engine::{self, SearchLimits};
+use crate::pgn::PgnGame;
+use crate::uci::{EngineConfig, GoCommand, UciMessage};
 use glib::clone;
 use gtk::prelude::*;
-use gtk::{glib, Application, ApplicationWindow, Button, Grid};
+use gtk::{gio, glib, Application, ApplicationWindow, Button, Grid};
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+mod board_view;
+mod clocks;
+mod external_engine;
+mod game_over;
 mod image;
+mod move_list;
+mod opening_explorer;
+mod preferences;
+use board_view::{BoardView, Coordinates};
+use external_engine::ExternalEngine;
+use game_over::GameOverChoice;
 use image::get_image;
+use preferences::Preferences;
 pub fn build_ui(app: &Application) {
     let window = ApplicationWindow::builder()
         .application(app)
@@ -15,173 +36,1108 @@ pub fn build_ui(app: &Application) {
         .default_width(HEIGHT)
         .default_height(WIDTH)
         .build();
-    let board = Rc::new(RefCell::new(Board::default()));
-    let grid = build_grid(board);
-    window.set_child(Some(&grid));
+    let preferences = Preferences::load();
+    let clock = new_clock(&preferences);
+    let view = GameView {
+        board: Rc::new(RefCell::new(Board::default())),
+        state: Rc::new(RefCell::new(UIBoardState::new(clock.as_ref()))),
+        board_view: BoardView::new(),
+        preferences: Rc::new(RefCell::new(preferences)),
+        opponent_dropdown: gtk::DropDown::builder().tooltip_text("Opponent").build(),
+        status_label: gtk::Label::builder()
+            .xalign(0.0)
+            .margin_start(6)
+            .margin_end(6)
+            .margin_top(3)
+            .margin_bottom(3)
+            .build(),
+        move_list: move_list::build_move_list(),
+        clock: Rc::new(RefCell::new(clock)),
+        white_clock: clocks::build_clock_label(),
+        black_clock: clocks::build_clock_label(),
+        external_opponent: Rc::new(RefCell::new(None)),
+        analyser: Rc::new(RefCell::new(None)),
+        analysis_label: gtk::Label::builder()
+            .wrap(true)
+            .xalign(0.0)
+            .selectable(true)
+            .margin_start(6)
+            .margin_end(6)
+            .build(),
+        book: Rc::new(opening_explorer::load_book()),
+        book_label: gtk::Label::builder()
+            .wrap(true)
+            .xalign(0.0)
+            .margin_start(6)
+            .margin_end(6)
+            .build(),
+    };
+    build_board_view(&view);
+    let header_bar = build_header_bar();
+    let engine_configs = external_engine::load_engine_configs();
+    init_opponent_dropdown(&view, &engine_configs);
+    header_bar.pack_start(&view.opponent_dropdown);
+    if !engine_configs.is_empty() {
+        header_bar.pack_start(&build_analysis_dropdown(&view, &engine_configs));
+    }
+    window.set_titlebar(Some(&header_bar));
+    add_navigation_actions(app, &window, &view);
+    add_preference_actions(&window, &view);
+    let move_list_window = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .min_content_width(180)
+        .vexpand(true)
+        .child(&view.move_list)
+        .build();
+    let side_panel = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    side_panel.append(&move_list_window);
+    side_panel.append(&view.analysis_label);
+    if view.book.is_some() {
+        side_panel.append(&view.book_label);
+        ui_show_book_moves(&view, &view.board.borrow());
+    }
+    // white plays up the board, so white's clock sits below it
+    let board_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    board_box.append(&view.black_clock);
+    board_box.append(&view.board_view);
+    board_box.append(&view.white_clock);
+    ui_update_clocks(&view);
+    glib::timeout_add_local(
+        Duration::from_millis(50),
+        clone!(
+            #[strong]
+            view,
+            move || {
+                ui_poll_engines(&view);
+                glib::ControlFlow::Continue
+            }
+        ),
+    );
+    glib::timeout_add_local(
+        Duration::from_millis(100),
+        clone!(
+            #[strong]
+            view,
+            move || {
+                ui_update_clocks(&view);
+                glib::ControlFlow::Continue
+            }
+        ),
+    );
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    content.append(&board_box);
+    content.append(&side_panel);
+    let window_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    window_box.append(&content);
+    window_box.append(&view.status_label);
+    window.set_child(Some(&window_box));
     window.present();
 }
+/// the game with the widgets that show it, shared by the signal handlers
+#[derive(Clone)]
+struct GameView {
+    board: Rc<RefCell<Board>>,
+    state: Rc<RefCell<UIBoardState>>,
+    board_view: BoardView,
+    preferences: Rc<RefCell<Preferences>>,
+    opponent_dropdown: gtk::DropDown,
+    /// whose turn it is, check and the result, under everything else
+    status_label: gtk::Label,
+    move_list: Grid,
+    /// `None` when the game is played without a clock
+    clock: Rc<RefCell<Option<Clock<SystemTime>>>>,
+    white_clock: gtk::Label,
+    black_clock: gtk::Label,
+    /// the engine behind `Opponent::External`, `None` until it has started
+    external_opponent: Rc<RefCell<Option<ExternalEngine>>>,
+    /// an engine analysing whatever position is shown
+    analyser: Rc<RefCell<Option<ExternalEngine>>>,
+    analysis_label: gtk::Label,
+    /// the opening book the built-in engine plays from and the explorer shows
+    book: Rc<Option<Book>>,
+    book_label: gtk::Label,
+}
+/// Who plays against the person at the board.
+#[derive(Clone, Copy, PartialEq)]
+enum Opponent {
+    Human,
+    /// the built-in engine, playing this color
+    BuiltIn(PieceColor),
+    /// the external engine in `GameView::external_opponent`, playing this color
+    External(PieceColor),
+}
+impl Opponent {
+    fn engine_color(self) -> Option<PieceColor> {
+        match self {
+            Opponent::Human => None,
+            Opponent::BuiltIn(color) | Opponent::External(color) => Some(color),
+        }
+    }
+}
 struct UIBoardState {
-    pressed_piece: Option<(usize, usize)>,
+    /// how the game ended, `None` while it goes on
+    result: Option<GameResult>,
+    /// moves taken back with undo, the next one to replay last
+    redo_stack: Vec<Move>,
+    /// SAN of every move in the line, including the ones on the redo stack
+    sans: Vec<String>,
+    /// the clock readings at the start of the game and after every move of the
+    /// line, so taking moves back takes their time back too. empty without a clock
+    clock_states: Vec<ClockState>,
+    opponent: Opponent,
+    /// set while the built-in engine is thinking, storing true makes it give up
+    engine_stop: Option<Arc<AtomicBool>>,
 }
 impl UIBoardState {
-    pub fn new() -> UIBoardState {
+    pub fn new(clock: Option<&Clock<SystemTime>>) -> UIBoardState {
         UIBoardState {
-            pressed_piece: None,
-        }
-    }
-}
-fn build_grid(board: Rc<RefCell<Board>>) -> Grid {
-    let grid = Grid::new();
-    let ui_board_state = Rc::new(RefCell::new(UIBoardState::new()));
-    grid.set_hexpand(true);
-    grid.set_vexpand(true);
-    for row in 0..ROWS {
-        for col in 0..COLS {
-            let cell_button = build_button(row, col, &board, &ui_board_state, &grid);
-            grid.attach(&cell_button, col as i32, row as i32, 1, 1);
-        }
-    }
-    grid
-}
-fn build_button(
-    row: usize,
-    col: usize,
-    board_ref_cell: &Rc<RefCell<Board>>,
-    ui_board_state: &Rc<RefCell<UIBoardState>>,
-    grid: &Grid,
-) -> Button {
-    let cell_button = Button::new();
-    cell_button.set_hexpand(true);
-    cell_button.set_vexpand(true);
-    cell_button.set_size_request(0, 0); // Allows dynamic resizing
-    if let Some(image) = get_image(board_ref_cell.borrow().get_piece(row, col)) {
-        cell_button.set_child(Some(&image));
-    }
-    cell_button.connect_clicked(clone!(
-        #[strong]
-        board_ref_cell,
+            result: None,
+            redo_stack: vec![],
+            sans: vec![],
+            clock_states: clock.map(Clock::state).into_iter().collect(),
+            opponent: Opponent::Human,
+            engine_stop: None,
+        }
+    }
+    /// abandons the built-in engine's search, its move will not be played
+    fn stop_engine(&mut self) {
+        if let Some(stop) = self.engine_stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+/// Ways of stepping through the moves played so far.
+#[derive(Clone, Copy)]
+enum Navigation {
+    First,
+    Previous,
+    Next,
+    Last,
+    /// back to the last position the person at the board had to move in
+    TakeBack,
+}
+fn build_header_bar() -> gtk::HeaderBar {
+    let header_bar = gtk::HeaderBar::new();
+    header_bar.pack_end(&build_preferences_menu());
+    let navigation_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    navigation_box.add_css_class("linked");
+    for (icon, action, tooltip) in [
+        ("go-first-symbolic", "win.first", "First move (Home)"),
+        (
+            "go-previous-symbolic",
+            "win.previous",
+            "Previous move (Left)",
+        ),
+        ("go-next-symbolic", "win.next", "Next move (Right)"),
+        ("go-last-symbolic", "win.last", "Last move (End)"),
+    ] {
+        let button = Button::from_icon_name(icon);
+        button.set_action_name(Some(action));
+        button.set_tooltip_text(Some(tooltip));
+        navigation_box.append(&button);
+    }
+    header_bar.pack_start(&navigation_box);
+    for (icon, action, tooltip) in [
+        (
+            "object-flip-vertical-symbolic",
+            "win.flip",
+            "Flip board (Ctrl+F)",
+        ),
+        ("document-open-symbolic", "win.open", "Open game (Ctrl+O)"),
+        ("edit-redo-symbolic", "win.redo", "Redo (Ctrl+Shift+Z)"),
+        ("edit-undo-symbolic", "win.undo", "Undo (Ctrl+Z)"),
+    ] {
+        let button = Button::from_icon_name(icon);
+        button.set_action_name(Some(action));
+        button.set_tooltip_text(Some(tooltip));
+        header_bar.pack_end(&button);
+    }
+    header_bar
+}
+/// lets the player pick a side against the built-in engine or an external one,
+/// or play both sides
+fn init_opponent_dropdown(view: &GameView, engine_configs: &[EngineConfig]) {
+    let mut labels = vec![
+        "Two players".to_string(),
+        "Play white vs computer".to_string(),
+        "Play black vs computer".to_string(),
+    ];
+    let mut choices = vec![
+        (Opponent::Human, None),
+        (Opponent::BuiltIn(PieceColor::Black), None),
+        (Opponent::BuiltIn(PieceColor::White), None),
+    ];
+    for config in engine_configs {
+        labels.push(format!("Play white vs {}", config.name));
+        choices.push((Opponent::External(PieceColor::Black), Some(config.clone())));
+        labels.push(format!("Play black vs {}", config.name));
+        choices.push((Opponent::External(PieceColor::White), Some(config.clone())));
+    }
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    let dropdown = &view.opponent_dropdown;
+    dropdown.set_model(Some(&gtk::StringList::new(&labels)));
+    dropdown.connect_selected_notify(clone!(
         #[strong]
-        ui_board_state,
+        view,
+        move |dropdown| {
+            let selected = dropdown.selected();
+            let Some((opponent, config)) = choices.get(selected as usize).cloned() else {
+                return;
+            };
+            {
+                let mut state = view.state.borrow_mut();
+                state.stop_engine();
+                state.opponent = opponent;
+            }
+            // the human's side goes at the bottom
+            if let Some(engine_color) = opponent.engine_color() {
+                ui_set_flipped(&view, engine_color == PieceColor::White);
+            }
+            // dropping an engine makes it quit
+            view.external_opponent.replace(None);
+            let Some(config) = config else {
+                ui_start_engine_if_its_turn(&view, &view.board.borrow());
+                ui_sync_board(&view, &view.board.borrow());
+                return;
+            };
+            ui_sync_board(&view, &view.board.borrow());
+            external_engine::start_engine(
+                config,
+                clone!(
+                    #[strong]
+                    view,
+                    #[weak]
+                    dropdown,
+                    move |started| {
+                        // the player picked another opponent while this one started
+                        if dropdown.selected() != selected {
+                            return;
+                        }
+                        match started {
+                            Ok(engine) => {
+                                view.external_opponent
+                                    .replace(Some(ExternalEngine::new(engine)));
+                                ui_start_engine_if_its_turn(&view, &view.board.borrow());
+                                ui_sync_board(&view, &view.board.borrow());
+                            }
+                            Err(e) => println!("Failed to start the engine: {}", e),
+                        }
+                    }
+                ),
+            );
+        }
+    ));
+}
+/// the opponent dropdown entry for the same opponent playing the other side. after
+/// "Two players" every opponent is listed playing black, then playing white
+fn swapped_sides_choice(selected: u32) -> u32 {
+    match selected {
+        0 => 0,
+        _ if selected % 2 == 1 => selected + 1,
+        _ => selected - 1,
+    }
+}
+/// lets the player have an external engine analyse the position on the board
+fn build_analysis_dropdown(view: &GameView, engine_configs: &[EngineConfig]) -> gtk::DropDown {
+    let mut labels = vec!["No analysis".to_string()];
+    labels.extend(
+        engine_configs
+            .iter()
+            .map(|config| format!("Analyse with {}", config.name)),
+    );
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    let dropdown = gtk::DropDown::from_strings(&labels);
+    dropdown.set_tooltip_text(Some("Analysis"));
+    let engine_configs = engine_configs.to_vec();
+    dropdown.connect_selected_notify(clone!(
         #[strong]
-        grid,
-        move |_| {
-            println!("Button clicked: ({}, {})", row, col);
-            if ui_board_state.borrow().pressed_piece.is_some() {
-                ui_reset_grid_color(&grid);
-                let (r, c) = ui_board_state.borrow().pressed_piece.unwrap();
-                ui_board_state.borrow_mut().pressed_piece = None;
-                let mut board = board_ref_cell.borrow_mut();
-                if let Ok(move_type) = board.try_move_piece((r, c), (row, col)) {
-                    match move_type {
-                        MoveType::Promotion(piece_promoted) => {
-                            ui_move_piece(r, c, row, col, &grid);
-                            ui_promote_pawn(&grid, row, col, &piece_promoted);
+        view,
+        move |dropdown| {
+            let selected = dropdown.selected();
+            view.analyser.replace(None);
+            view.analysis_label.set_text("");
+            let Some(config) = (selected as usize)
+                .checked_sub(1)
+                .and_then(|i| engine_configs.get(i))
+            else {
+                return;
+            };
+            external_engine::start_engine(
+                config.clone(),
+                clone!(
+                    #[strong]
+                    view,
+                    #[weak]
+                    dropdown,
+                    move |started| {
+                        if dropdown.selected() != selected {
+                            return;
                         }
-                        _ => {
-                            ui_move_piece(r, c, row, col, &grid);
+                        match started {
+                            Ok(engine) => {
+                                view.analyser.replace(Some(ExternalEngine::new(engine)));
+                                ui_analyse(&view, &view.board.borrow());
+                            }
+                            Err(e) => println!("Failed to start the engine: {}", e),
                         }
                     }
-                    if board.is_checkmate() {
-                        println!("Checkmate!");
+                ),
+            );
+        }
+    ));
+    dropdown
+}
+/// registers the undo, redo and move navigation actions on the window, with their shortcuts
+fn add_navigation_actions(app: &Application, window: &ApplicationWindow, view: &GameView) {
+    for (name, navigation, accels) in [
+        ("undo", Navigation::TakeBack, &["<Ctrl>z"][..]),
+        ("redo", Navigation::Next, &["<Ctrl><Shift>z", "<Ctrl>y"][..]),
+        ("first", Navigation::First, &["Home"][..]),
+        ("previous", Navigation::Previous, &["Left"][..]),
+        ("next", Navigation::Next, &["Right"][..]),
+        ("last", Navigation::Last, &["End"][..]),
+    ] {
+        let action = gio::ActionEntry::builder(name)
+            .activate(clone!(
+                #[strong]
+                view,
+                move |_: &ApplicationWindow, _, _| {
+                    ui_navigate(&view, navigation);
+                }
+            ))
+            .build();
+        window.add_action_entries([action]);
+        app.set_accels_for_action(&format!("win.{}", name), accels);
+    }
+    let flip = gio::ActionEntry::builder("flip")
+        .activate(clone!(
+            #[strong]
+            view,
+            move |_: &ApplicationWindow, _, _| {
+                ui_set_flipped(&view, !view.board_view.is_flipped());
+            }
+        ))
+        .build();
+    window.add_action_entries([flip]);
+    app.set_accels_for_action("win.flip", &["<Ctrl>f"]);
+    let open = gio::ActionEntry::builder("open")
+        .activate(clone!(
+            #[strong]
+            view,
+            move |window: &ApplicationWindow, _, _| {
+                game_over::open_pgn(
+                    Some(window.upcast_ref()),
+                    clone!(
+                        #[strong]
+                        view,
+                        move |game| ui_load_game(&view, &game)
+                    ),
+                );
+            }
+        ))
+        .build();
+    window.add_action_entries([open]);
+    app.set_accels_for_action("win.open", &["<Ctrl>o"]);
+}
+/// the menu of settings that are kept between sessions
+fn build_preferences_menu() -> gtk::MenuButton {
+    let coordinates = gio::Menu::new();
+    for choice in Coordinates::ALL {
+        coordinates.append(
+            Some(choice.label()),
+            Some(&format!("win.coordinates::{}", choice.name())),
+        );
+    }
+    let time_controls = gio::Menu::new();
+    for (label, tag) in clocks::TIME_CONTROLS {
+        let item = gio::MenuItem::new(Some(label), None);
+        item.set_action_and_target_value(Some("win.time-control"), Some(&tag.to_variant()));
+        time_controls.append_item(&item);
+    }
+    let menu = gio::Menu::new();
+    menu.append_section(None, &coordinates);
+    menu.append_section(Some("Clock"), &time_controls);
+    gtk::MenuButton::builder()
+        .icon_name("open-menu-symbolic")
+        .tooltip_text("Preferences")
+        .menu_model(&menu)
+        .build()
+}
+/// registers the actions behind the preferences menu, each of which shows its
+/// setting right away and saves it. a new time control waits for the next game,
+/// unless no move has been played yet
+fn add_preference_actions(window: &ApplicationWindow, view: &GameView) {
+    let coordinates = view.preferences.borrow().coordinates;
+    view.board_view.set_coordinates(coordinates);
+    let action = gio::ActionEntry::builder("coordinates")
+        .parameter_type(Some(&String::static_variant_type()))
+        .state(coordinates.name().to_variant())
+        .change_state(clone!(
+            #[strong]
+            view,
+            move |_: &ApplicationWindow, action, value| {
+                let Some(coordinates) = value
+                    .and_then(|value| value.str())
+                    .and_then(Coordinates::from_name)
+                else {
+                    return;
+                };
+                action.set_state(&coordinates.name().to_variant());
+                view.board_view.set_coordinates(coordinates);
+                let mut preferences = view.preferences.borrow_mut();
+                preferences.coordinates = coordinates;
+                preferences.save();
+            }
+        ))
+        .build();
+    let time_control = clocks::time_control_tag(view.preferences.borrow().time_control.as_ref());
+    let clock_action = gio::ActionEntry::builder("time-control")
+        .parameter_type(Some(&String::static_variant_type()))
+        .state(time_control.to_variant())
+        .change_state(clone!(
+            #[strong]
+            view,
+            move |_: &ApplicationWindow, action, value| {
+                let Some((tag, time_control)) = value
+                    .and_then(|value| value.str())
+                    .and_then(|tag| Some((tag, clocks::parse_time_control(tag)?)))
+                else {
+                    return;
+                };
+                action.set_state(&tag.to_variant());
+                {
+                    let mut preferences = view.preferences.borrow_mut();
+                    preferences.time_control = time_control;
+                    preferences.save();
+                }
+                let started = !view.board.borrow().history().is_empty()
+                    || !view.state.borrow().redo_stack.is_empty();
+                if !started {
+                    ui_reset_clock(&view);
+                }
+            }
+        ))
+        .build();
+    window.add_action_entries([action, clock_action]);
+}
+fn ui_navigate(view: &GameView, navigation: Navigation) {
+    let current_ply = view.board.borrow().history().len();
+    let ply = match navigation {
+        Navigation::First => 0,
+        Navigation::Previous => current_ply.saturating_sub(1),
+        Navigation::Next => current_ply + 1,
+        Navigation::Last => current_ply + view.state.borrow().redo_stack.len(),
+        Navigation::TakeBack => return ui_take_back(view),
+    };
+    ui_go_to_ply(view, ply);
+}
+/// takes back the last move, and the engine's reply to it too against the computer.
+/// if the engine moved first there is nothing of the player's to take back, so it
+/// goes to the start and the engine plays its first move again
+fn ui_take_back(view: &GameView) {
+    let (current_ply, turn) = {
+        let board = view.board.borrow();
+        (board.history().len(), board.turn_player)
+    };
+    let engine_color = view.state.borrow().opponent.engine_color();
+    let Some(engine_color) = engine_color else {
+        ui_go_to_ply(view, current_ply.saturating_sub(1));
+        return;
+    };
+    let to_move_at = |ply: usize| {
+        if (current_ply - ply).is_multiple_of(2) {
+            turn
+        } else {
+            turn.opposite()
+        }
+    };
+    match (0..current_ply)
+        .rev()
+        .find(|&ply| to_move_at(ply) != engine_color)
+    {
+        Some(ply) => ui_go_to_ply(view, ply),
+        None if current_ply > 0 => {
+            ui_go_to_ply(view, 0);
+            // its move replaces the line that was taken back
+            let board = view.board.borrow();
+            ui_start_engine_if_its_turn(view, &board);
+            ui_sync_board(view, &board);
+        }
+        None => (),
+    }
+}
+/// takes back or replays moves until `ply` moves of the line are on the board,
+/// then shows the board. back at the end of the line, the engine carries on if it
+/// is its turn
+fn ui_go_to_ply(view: &GameView, ply: usize) {
+    let mut board = view.board.borrow_mut();
+    {
+        let mut state = view.state.borrow_mut();
+        let start_ply = board.history().len();
+        while board.history().len() > ply {
+            match board.undo_move() {
+                Some(mv) => state.redo_stack.push(mv),
+                None => break,
+            }
+        }
+        while board.history().len() < ply {
+            match state.redo_stack.pop() {
+                Some(mv) => board.make_move(mv),
+                None => break,
+            }
+        }
+        if board.history().len() == start_ply {
+            return;
+        }
+        state.stop_engine();
+        // a fallen flag only ends the game at the end of the line, where it fell
+        let at_end = state.redo_stack.is_empty();
+        let mut clock = view.clock.borrow_mut();
+        let flagged = clock
+            .as_mut()
+            .filter(|_| at_end)
+            .and_then(|clock| clock.check_flag());
+        state.result = match board.game_status() {
+            GameStatus::Over(result) => Some(result),
+            GameStatus::Ongoing => flagged.map(|flagged| board.timeout_result(flagged)),
+        };
+        if let Some(clock) = clock.as_mut() {
+            // the clock waits while earlier positions are looked at
+            if at_end && state.result.is_none() && !board.history().is_empty() {
+                clock.start(board.turn_player);
+            } else {
+                clock.stop();
+            }
+        }
+    }
+    ui_stop_external_opponent(view);
+    if view.state.borrow().redo_stack.is_empty() {
+        ui_start_engine_if_its_turn(view, &board);
+    }
+    ui_sync_board(view, &board);
+    ui_refresh_move_list(view, board.history().len());
+    ui_analyse(view, &board);
+    ui_show_book_moves(view, &board);
+}
+fn ui_refresh_move_list(view: &GameView, current_ply: usize) {
+    let on_click: Rc<dyn Fn(usize)> = Rc::new(clone!(
+        #[strong]
+        view,
+        move |ply| ui_go_to_ply(&view, ply)
+    ));
+    move_list::refresh(
+        &view.move_list,
+        &view.state.borrow().sans,
+        current_ply,
+        on_click,
+    );
+}
+/// has the board view hand the moves the player makes to `ui_request_move`
+fn build_board_view(view: &GameView) {
+    ui_sync_board(view, &view.board.borrow());
+    view.board_view.connect_move_requested(clone!(
+        #[strong]
+        view,
+        move |_, from, to| ui_request_move(&view, from, to)
+    ));
+}
+/// turns the board so black's side is at the bottom, or white's if `flipped` is
+/// false. the clocks swap places with it
+fn ui_set_flipped(view: &GameView, flipped: bool) {
+    if view.board_view.is_flipped() == flipped {
+        return;
+    }
+    view.board_view.set_flipped(flipped);
+    if let Some(board_box) = view.board_view.parent().and_downcast::<gtk::Box>() {
+        let (top, bottom) = if flipped {
+            (&view.white_clock, &view.black_clock)
+        } else {
+            (&view.black_clock, &view.white_clock)
+        };
+        board_box.reorder_child_after(top, None::<&gtk::Widget>);
+        board_box.reorder_child_after(bottom, Some(&view.board_view));
+    }
+}
+/// shows the board in the board view, which lets the player move unless the
+/// game is over or it is the computer's turn
+fn ui_sync_board(view: &GameView, board: &Board) {
+    view.board_view.set_board(board);
+    let (result, opponent) = {
+        let state = view.state.borrow();
+        (state.result, state.opponent)
+    };
+    view.board_view.set_interactive(
+        result.is_none()
+            && opponent.engine_color() != Some(board.turn_player)
+            && !ui_engine_is_thinking(view),
+    );
+    view.status_label
+        .set_text(&game_over::status_text(board, result));
+}
+/// plays the move the player made on the board view, asking for the piece first
+/// if it promotes
+fn ui_request_move(view: &GameView, from: (usize, usize), to: (usize, usize)) {
+    if view.state.borrow().result.is_some() || ui_engine_is_thinking(view) {
+        return;
+    }
+    let mut board = view.board.borrow_mut();
+    if view.state.borrow().opponent.engine_color() == Some(board.turn_player) {
+        return;
+    }
+    if !board.is_promotion_move(from, to) {
+        ui_play_move(view, &mut board, from, to, None);
+        return;
+    }
+    let color = board.turn_player;
+    drop(board);
+    ui_choose_promotion(
+        &view.board_view,
+        to,
+        color,
+        clone!(
+            #[strong]
+            view,
+            move |promotion| {
+                let mut board = view.board.borrow_mut();
+                ui_play_move(&view, &mut board, from, to, Some(promotion));
+            }
+        ),
+    );
+}
+/// plays the move on the board and shows it on the board view and the move list.
+/// returns false if the move was illegal
+fn ui_play_move(
+    view: &GameView,
+    board: &mut Board,
+    (r, c): (usize, usize),
+    (row, col): (usize, usize),
+    promotion: Option<PromotionPiece>,
+) -> bool {
+    if !board.get_legal_moves((r, c)).contains(&(row, col)) {
+        return false;
+    }
+    let san = board.move_to_san((r, c), (row, col), promotion);
+    if board.try_move_piece((r, c), (row, col), promotion).is_err() {
+        return false;
+    }
+    let ply = board.history().len() - 1;
+    let mover = board.turn_player.opposite();
+    {
+        // a new move starts a new line, so the moves that were taken back are gone
+        let mut state = view.state.borrow_mut();
+        let took_back = !state.redo_stack.is_empty();
+        state.redo_stack.clear();
+        state.sans.truncate(ply);
+        state.sans.push(san);
+        if let Some(clock) = view.clock.borrow_mut().as_mut() {
+            // and so is the time they took
+            if took_back {
+                if let Some(&reading) = state.clock_states.get(ply) {
+                    clock.restore(reading);
+                    if ply > 0 {
+                        clock.start(mover);
                     }
-                } else if board.get_piece(row, col).color() == Some(board.turn_player) {
-                    ui_board_state.borrow_mut().pressed_piece = Some((row, col));
-                    let legal_moves = board.get_legal_moves((row, col));
-                    higlight_legal_moves(&grid, legal_moves, (row, col));
                 }
+            }
+            if clock.running() == Some(mover) {
+                clock.press();
             } else {
-                ui_board_state.borrow_mut().pressed_piece = Some((row, col));
-                let legal_moves = board_ref_cell.borrow().get_legal_moves((row, col));
-                higlight_legal_moves(&grid, legal_moves, (row, col));
+                clock.start(board.turn_player);
             }
+            state.clock_states.truncate(ply + 1);
+            state.clock_states.push(clock.state());
+        }
+    }
+    let status = board.game_status();
+    if let GameStatus::Over(result) = status {
+        view.state.borrow_mut().result = Some(result);
+        if let Some(clock) = view.clock.borrow_mut().as_mut() {
+            clock.stop();
+        }
+    }
+    ui_refresh_move_list(view, board.history().len());
+    ui_analyse(view, board);
+    ui_show_book_moves(view, board);
+    ui_start_engine_if_its_turn(view, board);
+    ui_sync_board(view, board);
+    if let GameStatus::Over(result) = status {
+        ui_show_result(view, board, result);
+    }
+    true
+}
+/// whether the opponent engine is working on a move, built-in or external
+fn ui_engine_is_thinking(view: &GameView) -> bool {
+    view.state.borrow().engine_stop.is_some()
+        || view
+            .external_opponent
+            .borrow()
+            .as_ref()
+            .is_some_and(|engine| engine.is_searching())
+}
+/// has the opponent engine look for a move if it is its turn. the built-in one plays
+/// from the book while it can, otherwise it searches off the main thread. its move
+/// is played unless the game moved on in the meantime, an external one answers
+/// through `ui_poll_engines`
+fn ui_start_engine_if_its_turn(view: &GameView, board: &Board) {
+    let mut state = view.state.borrow_mut();
+    if state.result.is_some()
+        || state.engine_stop.is_some()
+        || state.opponent.engine_color() != Some(board.turn_player)
+    {
+        return;
+    }
+    // don't let the computer lose on time when the clock is short
+    let mut move_time = Duration::from_millis(ENGINE_MOVE_TIME_MS);
+    if let Some(clock) = view.clock.borrow().as_ref() {
+        move_time = move_time.min(clock.remaining(board.turn_player) / 30);
+    }
+    if let Opponent::External(_) = state.opponent {
+        let mut external_opponent = view.external_opponent.borrow_mut();
+        // one that is still starting gets the position once it is ready
+        let Some(engine) = external_opponent.as_mut() else {
+            return;
+        };
+        if engine.is_searching() {
+            return;
+        }
+        let go = GoCommand {
+            move_time: Some(move_time),
+            ..GoCommand::default()
+        };
+        if let Err(e) = engine.search(board, &go) {
+            println!("{}: {}", engine.name(), e);
+            *external_opponent = None;
+        }
+        return;
+    }
+    let book_move = view
+        .book
+        .as_ref()
+        .as_ref()
+        .and_then(|book| book.pick_move(board));
+    let stop = Arc::new(AtomicBool::new(false));
+    state.engine_stop = Some(stop.clone());
+    let board = board.clone();
+    glib::spawn_future_local(clone!(
+        #[strong]
+        view,
+        async move {
+            let search_stop = stop.clone();
+            let best_move = match book_move {
+                Some(mv) => Some(mv),
+                None => gio::spawn_blocking(move || {
+                    engine::search(
+                        &board,
+                        &SearchLimits::move_time(move_time),
+                        &search_stop,
+                        |_| {},
+                    )
+                })
+                .await
+                .ok()
+                .and_then(|result| result.best_move),
+            };
+            {
+                let mut state = view.state.borrow_mut();
+                let still_wanted = state
+                    .engine_stop
+                    .as_ref()
+                    .is_some_and(|current| Arc::ptr_eq(current, &stop));
+                if !still_wanted {
+                    return;
+                }
+                state.engine_stop = None;
+            }
+            let Some(mv) = best_move else {
+                return;
+            };
+            let mut board = view.board.borrow_mut();
+            ui_play_move(&view, &mut board, mv.from, mv.to, mv.promotion);
         }
     ));
-
-    let is_black = (row + col) % 2 == 1;
-    let css = if is_black { BLACK_CSS } else { WHITE_CSS };
-    let provider = gtk::CssProvider::new();
-    provider.load_from_data(css);
-
-    cell_button
-        .style_context()
-        .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-
-    cell_button
-}
-fn ui_move_piece(r1: usize, c1: usize, r2: usize, c2: usize, grid: &Grid) {
-    // Get buttons at source and destination positions
-    let button1 = grid
-        .child_at(c1 as i32, r1 as i32)
-        .and_downcast::<gtk::Button>()
-        .expect("Failed to get button1");
-    let button2 = grid
-        .child_at(c2 as i32, r2 as i32)
-        .and_downcast::<gtk::Button>()
-        .expect("Failed to get button2");
-
-    let image1 = button1
-        .child()
-        .and_then(|child| child.downcast_ref::<gtk::Image>().cloned());
-    button1.set_child(GTK_NONE);
-    if let Some(image) = image1 {
-        button2.set_child(Some(&image));
+}
+fn ui_stop_external_opponent(view: &GameView) {
+    let mut external_opponent = view.external_opponent.borrow_mut();
+    if let Some(engine) = external_opponent.as_mut() {
+        if let Err(e) = engine.stop() {
+            println!("{}: {}", engine.name(), e);
+            *external_opponent = None;
+        }
+    }
+}
+/// points the analysing engine, if there is one, at the board
+fn ui_analyse(view: &GameView, board: &Board) {
+    let mut analyser = view.analyser.borrow_mut();
+    let Some(engine) = analyser.as_mut() else {
+        return;
+    };
+    view.analysis_label.set_text("");
+    let result = if board.is_checkmate() || board.is_stalemate() {
+        engine.stop()
     } else {
-        button2.set_child(GTK_NONE);
-    }
-}
-fn ui_reset_grid_color(grid: &Grid) {
-    for r in 0..ROWS {
-        for c in 0..COLS {
-            let button = grid
-                .child_at(c as i32, r as i32)
-                .and_downcast::<gtk::Button>()
-                .expect("Failed to get button");
-            let is_black = (r + c) % 2 == 1;
-            let css = if is_black { BLACK_CSS } else { WHITE_CSS };
-            let provider = gtk::CssProvider::new();
-            provider.load_from_data(css);
-            button
-                .style_context()
-                .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-        }
-    }
-}
-fn higlight_legal_moves(grid: &Grid, legal_moves: Vec<(usize, usize)>, from: (usize, usize)) {
-    highlight_chosen_square(grid, from.0, from.1);
-    highlight_squares_to_go_to(grid, legal_moves);
-}
-fn highlight_chosen_square(grid: &Grid, row: usize, col: usize) {
-    let button = grid
-        .child_at(col as i32, row as i32)
-        .and_downcast::<gtk::Button>()
-        .expect("Failed to get button");
-    let css = "button { background-color:rgb(47, 0, 255); }";
-    let provider = gtk::CssProvider::new();
-    provider.load_from_data(css);
-    button
-        .style_context()
-        .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-}
-
-fn highlight_squares_to_go_to(grid: &Grid, legal_moves: Vec<(usize, usize)>) {
-    for (r, c) in legal_moves {
-        let button = grid
-            .child_at(c as i32, r as i32)
-            .and_downcast::<gtk::Button>()
-            .expect("Failed to get button");
-        let css = "button { background-color: #ff0000; }"; // Red square
-        let provider = gtk::CssProvider::new();
-        provider.load_from_data(css);
-        button
-            .style_context()
-            .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
-    }
-}
-fn ui_promote_pawn(grid: &Grid, row: usize, col: usize, piece_promoted: &Piece) {
-    let button = grid
-        .child_at(col as i32, row as i32)
-        .and_downcast::<gtk::Button>()
-        .expect("Failed to get button");
-    let image = get_image(piece_promoted).expect("Failed to get image of a promoted peice");
-    button.set_child(Some(&image));
+        engine.search(
+            board,
+            &GoCommand {
+                infinite: true,
+                ..GoCommand::default()
+            },
+        )
+    };
+    if let Err(e) = result {
+        println!("{}: {}", engine.name(), e);
+        *analyser = None;
+    }
+}
+/// lists the book moves for the board in the opening explorer
+fn ui_show_book_moves(view: &GameView, board: &Board) {
+    if let Some(book) = view.book.as_ref() {
+        view.book_label
+            .set_text(&opening_explorer::format_book_moves(board, book));
+    }
+}
+/// plays the external opponent's moves and shows the analyser's latest line
+fn ui_poll_engines(view: &GameView) {
+    let polled = view
+        .external_opponent
+        .borrow_mut()
+        .as_mut()
+        .map(|engine| engine.poll());
+    match polled {
+        Some(Ok(messages)) => {
+            for message in messages {
+                let UciMessage::BestMove { best_move, .. } = message else {
+                    continue;
+                };
+                if view.state.borrow().result.is_some() {
+                    continue;
+                }
+                let mut board = view.board.borrow_mut();
+                match board.parse_uci_move(&best_move) {
+                    Some(mv) => {
+                        ui_play_move(view, &mut board, mv.from, mv.to, mv.promotion);
+                    }
+                    None => {
+                        println!("The engine played an illegal move: {}", best_move);
+                        ui_sync_board(view, &board);
+                    }
+                }
+            }
+        }
+        Some(Err(e)) => {
+            println!("{}", e);
+            view.external_opponent.replace(None);
+            ui_sync_board(view, &view.board.borrow());
+        }
+        None => (),
+    }
+    let polled = view
+        .analyser
+        .borrow_mut()
+        .as_mut()
+        .map(|engine| engine.poll());
+    match polled {
+        Some(Ok(messages)) => {
+            let latest = messages
+                .into_iter()
+                .rev()
+                .find_map(|message| match message {
+                    UciMessage::Info(info)
+                        if !info.pv.is_empty() && info.multipv.unwrap_or(1) == 1 =>
+                    {
+                        Some(info)
+                    }
+                    _ => None,
+                });
+            if let Some(text) = latest
+                .and_then(|info| external_engine::format_analysis(&view.board.borrow(), &info))
+            {
+                view.analysis_label.set_text(&text);
+            }
+        }
+        Some(Err(e)) => {
+            println!("{}", e);
+            view.analyser.replace(None);
+            view.analysis_label.set_text("");
+        }
+        None => (),
+    }
+}
+/// shows both clocks, or hides them when playing without one, and ends the game
+/// if the side to move has run out of time
+fn ui_update_clocks(view: &GameView) {
+    let mut clock = view.clock.borrow_mut();
+    view.white_clock.set_visible(clock.is_some());
+    view.black_clock.set_visible(clock.is_some());
+    let Some(clock) = clock.as_mut() else {
+        return;
+    };
+    let flagged = clock.check_flag();
+    for (label, color) in [
+        (&view.white_clock, PieceColor::White),
+        (&view.black_clock, PieceColor::Black),
+    ] {
+        clocks::update_clock_label(
+            label,
+            clock.remaining(color),
+            clock.running() == Some(color),
+        );
+    }
+    let Some(flagged) = flagged else {
+        return;
+    };
+    let mut state = view.state.borrow_mut();
+    // earlier positions being looked at are not where the flag fell
+    if state.result.is_none() && state.redo_stack.is_empty() {
+        let board = view.board.borrow();
+        let result = board.timeout_result(flagged);
+        state.result = Some(result);
+        state.stop_engine();
+        drop(state);
+        ui_stop_external_opponent(view);
+        ui_sync_board(view, &board);
+        ui_show_result(view, &board, result);
+    }
+}
+/// tells the players how the game ended and lets them start another one
+fn ui_show_result(view: &GameView, board: &Board, result: GameResult) {
+    let parent = view.board_view.root().and_downcast::<gtk::Window>();
+    game_over::show_result_dialog(
+        parent.as_ref(),
+        board,
+        result,
+        clone!(
+            #[strong]
+            view,
+            move |choice| match choice {
+                GameOverChoice::NewGame => ui_new_game(&view),
+                GameOverChoice::Rematch => ui_rematch(&view),
+            }
+        ),
+    );
+}
+/// starts over from the starting position with a fresh clock, against the same opponent
+fn ui_new_game(view: &GameView) {
+    {
+        let mut state = view.state.borrow_mut();
+        state.stop_engine();
+        state.result = None;
+        state.redo_stack.clear();
+        state.sans.clear();
+    }
+    ui_stop_external_opponent(view);
+    view.board.replace(Board::default());
+    ui_reset_clock(view);
+    let board = view.board.borrow();
+    ui_refresh_move_list(view, 0);
+    ui_analyse(view, &board);
+    ui_show_book_moves(view, &board);
+    ui_start_engine_if_its_turn(view, &board);
+    ui_sync_board(view, &board);
+}
+/// replaces the game with the main line of `game`, shown at its last position with
+/// two people to carry on from there. the clock starts afresh, as the moves kept no
+/// time
+fn ui_load_game(view: &GameView, game: &PgnGame) {
+    let board = match game.final_board() {
+        Ok(board) => board,
+        Err(e) => {
+            println!("cannot open the game: {}", e);
+            return;
+        }
+    };
+    // an engine should not take over someone else's game
+    view.opponent_dropdown.set_selected(0);
+    {
+        let mut state = view.state.borrow_mut();
+        state.stop_engine();
+        state.result = match board.game_status() {
+            GameStatus::Over(result) => Some(result),
+            GameStatus::Ongoing => None,
+        };
+        state.redo_stack.clear();
+        state.sans = PgnGame::from_board(&board)
+            .moves
+            .into_iter()
+            .map(|mv| mv.san)
+            .collect();
+    }
+    ui_stop_external_opponent(view);
+    view.board.replace(board);
+    ui_reset_clock(view);
+    {
+        let mut state = view.state.borrow_mut();
+        if let Some(&start) = state.clock_states.first() {
+            state.clock_states = vec![start; game.moves.len() + 1];
+        }
+    }
+    let board = view.board.borrow();
+    ui_refresh_move_list(view, board.history().len());
+    ui_analyse(view, &board);
+    ui_show_book_moves(view, &board);
+    ui_sync_board(view, &board);
+}
+/// a clock that has not started yet for the time control in the preferences
+fn new_clock(preferences: &Preferences) -> Option<Clock<SystemTime>> {
+    preferences
+        .time_control
+        .clone()
+        .map(|control| Clock::new(control, SystemTime::new()))
+}
+/// replaces the clock with a fresh one for the time control in the preferences
+fn ui_reset_clock(view: &GameView) {
+    let clock = new_clock(&view.preferences.borrow());
+    view.state.borrow_mut().clock_states = clock.as_ref().map(Clock::state).into_iter().collect();
+    view.clock.replace(clock);
+    ui_update_clocks(view);
+}
+/// starts a new game with the sides swapped, turning the board to go with them
+fn ui_rematch(view: &GameView) {
+    ui_new_game(view);
+    let opponent = view.state.borrow().opponent;
+    if opponent == Opponent::Human {
+        ui_set_flipped(view, !view.board_view.is_flipped());
+        return;
+    }
+    // picking the same opponent playing the other side restarts it on that side
+    let dropdown = &view.opponent_dropdown;
+    dropdown.set_selected(swapped_sides_choice(dropdown.selected()));
+}
+/// pops up the four promotion pieces over the promotion square and calls
+/// `on_chosen` with the one the player clicks
+fn ui_choose_promotion<F: Fn(PromotionPiece) + 'static>(
+    board_view: &BoardView,
+    square: (usize, usize),
+    color: PieceColor,
+    on_chosen: F,
+) {
+    let on_chosen = Rc::new(on_chosen);
+    let popover = gtk::Popover::new();
+    let pieces_box = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    for promotion in PromotionPiece::ALL {
+        let piece_button = Button::new();
+        if let Some(image) = get_image(&promotion.to_piece(color)) {
+            image.set_pixel_size(48);
+            piece_button.set_child(Some(&image));
+        }
+        piece_button.connect_clicked(clone!(
+            #[strong]
+            on_chosen,
+            #[weak]
+            popover,
+            move |_| {
+                popover.popdown();
+                on_chosen(promotion);
+            }
+        ));
+        pieces_box.append(&piece_button);
+    }
+    popover.set_child(Some(&pieces_box));
+    popover.set_parent(board_view);
+    popover.set_pointing_to(Some(&board_view.square_rect(square)));
+    // the popover is created per promotion, so drop it from the board once it closes
+    popover.connect_closed(|popover| {
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+    popover.popup();
 }
@@ -0,0 +1,582 @@
+//! The board as a single widget that draws the squares, pieces, highlights and
+//! coordinates itself, scaled to whatever room it gets.
+//!
+//! Pieces are moved by clicking them and then their target, or by dragging them.
+//! The widget only lets the side to move pick its pieces up and only offers their
+//! legal moves; playing one is left to whoever handles `move-requested`.
+use super::image::piece_file;
+use crate::board::piece::Piece;
+use crate::board::{square_name, Board};
+use crate::consts::{
+    CHECK_COLOR, COLS, DARK_SQUARE_COLOR, LAST_MOVE_COLOR, LIGHT_SQUARE_COLOR, MOVE_HINT_COLOR,
+    ROWS, SELECTED_SQUARE_COLOR,
+};
+use glib::clone;
+use glib::subclass::Signal;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, glib, graphene, gsk, pango};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// the smallest a square gets, in pixels
+const MIN_SQUARE_SIZE: i32 = 24;
+/// the size of a square the widget asks for
+const NATURAL_SQUARE_SIZE: i32 = 72;
+
+/// the width of the margin the coordinates go in, in squares
+const MARGIN: f64 = 0.4;
+
+/// Where the board shows the files and ranks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Coordinates {
+    Hidden,
+    /// in the corners of the squares along the bottom and left edges
+    #[default]
+    InSquares,
+    /// in a margin around the board
+    InMargin,
+}
+impl Coordinates {
+    pub const ALL: [Coordinates; 3] = [
+        Coordinates::Hidden,
+        Coordinates::InSquares,
+        Coordinates::InMargin,
+    ];
+    /// the name the choice is saved under
+    pub fn name(self) -> &'static str {
+        match self {
+            Coordinates::Hidden => "hidden",
+            Coordinates::InSquares => "squares",
+            Coordinates::InMargin => "margin",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Coordinates> {
+        Coordinates::ALL
+            .into_iter()
+            .find(|coordinates| coordinates.name() == name)
+    }
+    /// how the choice is shown in the menu
+    pub fn label(self) -> &'static str {
+        match self {
+            Coordinates::Hidden => "No coordinates",
+            Coordinates::InSquares => "Coordinates in the squares",
+            Coordinates::InMargin => "Coordinates around the board",
+        }
+    }
+}
+
+/// A piece picked up with the pointer.
+#[derive(Clone, Copy)]
+struct Drag {
+    from: (usize, usize),
+    /// where the pointer is, in widget coordinates
+    x: f64,
+    y: f64,
+    /// false until the pointer has gone far enough to tell a drag from a click
+    moving: bool,
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct BoardView {
+        pub(super) board: RefCell<Board>,
+        /// true when black's side of the board is at the bottom
+        pub(super) flipped: Cell<bool>,
+        /// whether the player may pick pieces up
+        pub(super) interactive: Cell<bool>,
+        pub(super) selected: Cell<Option<(usize, usize)>>,
+        /// the legal moves of the selected piece
+        pub(super) targets: RefCell<Vec<(usize, usize)>>,
+        pub(super) drag: Cell<Option<Drag>>,
+        pub(super) coordinates: Cell<Coordinates>,
+        /// the piece pictures by file, `None` for one that failed to load
+        pub(super) textures: RefCell<HashMap<&'static str, Option<gdk::Texture>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BoardView {
+        const NAME: &'static str = "XpChessBoardView";
+        type Type = super::BoardView;
+        type ParentType = gtk::Widget;
+    }
+
+    impl ObjectImpl for BoardView {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                // the from and to squares, as `row * COLS + col`
+                vec![Signal::builder("move-requested")
+                    .param_types([u32::static_type(), u32::static_type()])
+                    .build()]
+            })
+        }
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.set_hexpand(true);
+            obj.set_vexpand(true);
+            self.interactive.set(true);
+            obj.add_pointer_gesture();
+        }
+        fn dispose(&self) {
+            // the promotion popovers
+            while let Some(child) = self.obj().first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for BoardView {
+        fn measure(&self, _orientation: gtk::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            (
+                MIN_SQUARE_SIZE * ROWS as i32,
+                NATURAL_SQUARE_SIZE * ROWS as i32,
+                -1,
+                -1,
+            )
+        }
+        fn size_allocate(&self, width: i32, height: i32, baseline: i32) {
+            self.parent_size_allocate(width, height, baseline);
+            let mut child = self.obj().first_child();
+            while let Some(widget) = child {
+                if let Some(popover) = widget.downcast_ref::<gtk::Popover>() {
+                    popover.present();
+                }
+                child = widget.next_sibling();
+            }
+        }
+        fn snapshot(&self, snapshot: &gtk::Snapshot) {
+            self.obj().draw(snapshot);
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct BoardView(ObjectSubclass<imp::BoardView>)
+        @extends gtk::Widget;
+}
+
+impl Default for BoardView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardView {
+    pub fn new() -> BoardView {
+        glib::Object::new()
+    }
+    /// shows the position, putting down any piece that was picked up
+    pub fn set_board(&self, board: &Board) {
+        self.imp().board.replace(board.clone());
+        self.clear_selection();
+    }
+    pub fn is_flipped(&self) -> bool {
+        self.imp().flipped.get()
+    }
+    pub fn set_flipped(&self, flipped: bool) {
+        self.imp().flipped.set(flipped);
+        self.queue_draw();
+    }
+    pub fn set_coordinates(&self, coordinates: Coordinates) {
+        self.imp().coordinates.set(coordinates);
+        self.queue_draw();
+    }
+    /// whether the player may pick pieces up, e.g. not while the computer thinks
+    pub fn set_interactive(&self, interactive: bool) {
+        self.imp().interactive.set(interactive);
+        if !interactive {
+            self.clear_selection();
+        }
+    }
+    /// calls `f` with the from and to squares when the player makes a legal move
+    pub fn connect_move_requested<F: Fn(&BoardView, (usize, usize), (usize, usize)) + 'static>(
+        &self,
+        f: F,
+    ) -> glib::SignalHandlerId {
+        self.connect_closure(
+            "move-requested",
+            false,
+            glib::closure_local!(move |board_view: &BoardView, from: u32, to: u32| {
+                f(board_view, index_square(from), index_square(to));
+            }),
+        )
+    }
+    /// the square's area in widget coordinates, e.g. to point a popover at it
+    pub fn square_rect(&self, square: (usize, usize)) -> gdk::Rectangle {
+        let (side, _, _) = self.geometry();
+        let (x, y) = self.square_origin(square);
+        gdk::Rectangle::new(x as i32, y as i32, side as i32, side as i32)
+    }
+
+    /// the side of a square and the top left corner of the board, which is
+    /// centred in the widget, leaving room for the coordinates if they go around it
+    fn geometry(&self) -> (f64, f64, f64) {
+        let width = self.width() as f64;
+        let height = self.height() as f64;
+        let margins = match self.imp().coordinates.get() {
+            Coordinates::InMargin => 2.0 * MARGIN,
+            _ => 0.0,
+        };
+        let side = (width / (COLS as f64 + margins)).min(height / (ROWS as f64 + margins));
+        let x = (width - side * COLS as f64) / 2.0;
+        let y = (height - side * ROWS as f64) / 2.0;
+        (side, x, y)
+    }
+    /// the square shown in the given column and row of the widget. turning the
+    /// board half way round is its own inverse, so this also maps squares to
+    /// where they are shown
+    fn display_square(&self, (row, col): (usize, usize)) -> (usize, usize) {
+        if self.is_flipped() {
+            (ROWS - 1 - row, COLS - 1 - col)
+        } else {
+            (row, col)
+        }
+    }
+    fn square_origin(&self, square: (usize, usize)) -> (f64, f64) {
+        let (side, x, y) = self.geometry();
+        let (row, col) = self.display_square(square);
+        (x + col as f64 * side, y + row as f64 * side)
+    }
+    fn square_at(&self, x: f64, y: f64) -> Option<(usize, usize)> {
+        let (side, left, top) = self.geometry();
+        let col = ((x - left) / side).floor();
+        let row = ((y - top) / side).floor();
+        if col < 0.0 || row < 0.0 || col >= COLS as f64 || row >= ROWS as f64 {
+            return None;
+        }
+        Some(self.display_square((row as usize, col as usize)))
+    }
+
+    fn add_pointer_gesture(&self) {
+        let gesture = gtk::GestureDrag::new();
+        gesture.set_button(gdk::BUTTON_PRIMARY);
+        gesture.connect_drag_begin(clone!(
+            #[weak(rename_to = board_view)]
+            self,
+            move |_, x, y| board_view.press(x, y)
+        ));
+        gesture.connect_drag_update(clone!(
+            #[weak(rename_to = board_view)]
+            self,
+            move |gesture, dx, dy| {
+                if let Some((x, y)) = gesture.start_point() {
+                    board_view.drag_to(x + dx, y + dy, dx.hypot(dy));
+                }
+            }
+        ));
+        gesture.connect_drag_end(clone!(
+            #[weak(rename_to = board_view)]
+            self,
+            move |gesture, dx, dy| {
+                if let Some((x, y)) = gesture.start_point() {
+                    board_view.release(x + dx, y + dy);
+                }
+            }
+        ));
+        gesture.connect_cancel(clone!(
+            #[weak(rename_to = board_view)]
+            self,
+            move |_, _| {
+                board_view.imp().drag.set(None);
+                board_view.queue_draw();
+            }
+        ));
+        self.add_controller(gesture);
+    }
+    /// picks up a piece of the side to move, or plays the selected one to the square
+    fn press(&self, x: f64, y: f64) {
+        let imp = self.imp();
+        if !imp.interactive.get() {
+            return;
+        }
+        let Some(square) = self.square_at(x, y) else {
+            self.clear_selection();
+            return;
+        };
+        let own_piece = {
+            let board = imp.board.borrow();
+            board.get_piece(square.0, square.1).color() == Some(board.turn_player)
+        };
+        if own_piece {
+            self.select(square);
+            imp.drag.set(Some(Drag {
+                from: square,
+                x,
+                y,
+                moving: false,
+            }));
+        } else if let Some(from) = imp.selected.get() {
+            let legal = imp.targets.borrow().contains(&square);
+            self.clear_selection();
+            if legal {
+                self.emit_move_requested(from, square);
+            }
+        }
+    }
+    fn drag_to(&self, x: f64, y: f64, distance: f64) {
+        let imp = self.imp();
+        let Some(mut drag) = imp.drag.get() else {
+            return;
+        };
+        let threshold = self.settings().gtk_dnd_drag_threshold() as f64;
+        drag.moving |= distance >= threshold;
+        drag.x = x;
+        drag.y = y;
+        imp.drag.set(Some(drag));
+        self.queue_draw();
+    }
+    /// drops a dragged piece, which goes back to its square unless the move is
+    /// legal. a piece that was only clicked stays selected
+    fn release(&self, x: f64, y: f64) {
+        let imp = self.imp();
+        let Some(drag) = imp.drag.take() else {
+            return;
+        };
+        if !drag.moving {
+            self.queue_draw();
+            return;
+        }
+        let to = self
+            .square_at(x, y)
+            .filter(|to| imp.targets.borrow().contains(to));
+        self.clear_selection();
+        if let Some(to) = to {
+            self.emit_move_requested(drag.from, to);
+        }
+    }
+    fn select(&self, square: (usize, usize)) {
+        let imp = self.imp();
+        imp.selected.set(Some(square));
+        imp.targets
+            .replace(imp.board.borrow().get_legal_moves(square));
+        self.queue_draw();
+    }
+    fn clear_selection(&self) {
+        let imp = self.imp();
+        imp.selected.set(None);
+        imp.targets.borrow_mut().clear();
+        imp.drag.set(None);
+        self.queue_draw();
+    }
+    fn emit_move_requested(&self, from: (usize, usize), to: (usize, usize)) {
+        self.emit_by_name::<()>("move-requested", &[&square_index(from), &square_index(to)]);
+    }
+
+    fn draw(&self, snapshot: &gtk::Snapshot) {
+        let imp = self.imp();
+        let (side, _, _) = self.geometry();
+        let board = imp.board.borrow();
+        let selected = imp.selected.get();
+        let targets = imp.targets.borrow();
+        let drag = imp.drag.get().filter(|drag| drag.moving);
+        let last_move = board.last_move().map(|mv| [mv.from, mv.to]);
+        let checked_king = board.checked_king();
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let square = (row, col);
+                let (x, y) = self.square_origin(square);
+                let rect = graphene::Rect::new(x as f32, y as f32, side as f32, side as f32);
+                snapshot.append_color(&rgba(square_color(square)), &rect);
+                if last_move.is_some_and(|squares| squares.contains(&square)) {
+                    snapshot.append_color(&rgba(LAST_MOVE_COLOR), &rect);
+                }
+                if selected == Some(square) {
+                    snapshot.append_color(&rgba(SELECTED_SQUARE_COLOR), &rect);
+                }
+                if checked_king == Some(square) {
+                    draw_check_glow(snapshot, &rect);
+                }
+                let piece = board.get_piece(row, col);
+                if drag.is_none_or(|drag| drag.from != square) {
+                    self.draw_piece(snapshot, piece, &rect);
+                }
+                if targets.contains(&square) {
+                    let captures = !matches!(piece, Piece::Empty(_))
+                        || board.en_passant_target() == Some(square)
+                            && selected.is_some_and(|(row, col)| {
+                                matches!(board.get_piece(row, col), Piece::Pawn(_))
+                            });
+                    draw_move_hint(snapshot, &rect, captures);
+                }
+            }
+        }
+        self.draw_coordinates(snapshot);
+        // the dragged piece goes over everything else, centred on the pointer
+        if let Some(drag) = drag {
+            let (row, col) = drag.from;
+            let rect = graphene::Rect::new(
+                (drag.x - side / 2.0) as f32,
+                (drag.y - side / 2.0) as f32,
+                side as f32,
+                side as f32,
+            );
+            self.draw_piece(snapshot, board.get_piece(row, col), &rect);
+        }
+    }
+    fn draw_piece(&self, snapshot: &gtk::Snapshot, piece: &Piece, rect: &graphene::Rect) {
+        if let Some(texture) = self.texture(piece) {
+            snapshot.append_texture(&texture, rect);
+        }
+    }
+    /// the files along the bottom edge and the ranks along the left one, either
+    /// in the corners of the squares or in the margin around the board
+    fn draw_coordinates(&self, snapshot: &gtk::Snapshot) {
+        let coordinates = self.imp().coordinates.get();
+        if coordinates == Coordinates::Hidden {
+            return;
+        }
+        let (side, _, _) = self.geometry();
+        let margin = side * MARGIN;
+        let mut font = pango::FontDescription::new();
+        font.set_weight(pango::Weight::Bold);
+        let font_size = match coordinates {
+            Coordinates::InMargin => margin * 0.6,
+            _ => side * 0.18,
+        };
+        font.set_absolute_size(font_size * pango::SCALE as f64);
+        #[allow(deprecated)]
+        let foreground = self.style_context().color();
+        let padding = side * 0.05;
+        for i in 0..COLS {
+            let file_square = self.display_square((ROWS - 1, i));
+            let rank_square = self.display_square((i, 0));
+            let (file_x, file_y) = self.square_origin(file_square);
+            let (rank_x, rank_y) = self.square_origin(rank_square);
+            let file = &square_name(file_square)[..1];
+            let rank = &square_name(rank_square)[1..];
+            if coordinates == Coordinates::InMargin {
+                self.draw_label(snapshot, file, &font, foreground, |width, height| {
+                    (
+                        file_x + (side - width) / 2.0,
+                        file_y + side + (margin - height) / 2.0,
+                    )
+                });
+                self.draw_label(snapshot, rank, &font, foreground, |width, height| {
+                    (
+                        rank_x - margin + (margin - width) / 2.0,
+                        rank_y + (side - height) / 2.0,
+                    )
+                });
+            } else {
+                let file_color = rgba(contrasting_color(file_square));
+                self.draw_label(snapshot, file, &font, file_color, |width, height| {
+                    (
+                        file_x + side - width - padding,
+                        file_y + side - height - padding,
+                    )
+                });
+                let rank_color = rgba(contrasting_color(rank_square));
+                self.draw_label(snapshot, rank, &font, rank_color, |_, _| {
+                    (rank_x + padding, rank_y + padding)
+                });
+            }
+        }
+    }
+    /// writes `text` at the point `position` gives for its size
+    fn draw_label(
+        &self,
+        snapshot: &gtk::Snapshot,
+        text: &str,
+        font: &pango::FontDescription,
+        color: gdk::RGBA,
+        position: impl Fn(f64, f64) -> (f64, f64),
+    ) {
+        let layout = self.create_pango_layout(Some(text));
+        layout.set_font_description(Some(font));
+        let (_, extents) = layout.pixel_extents();
+        let (x, y) = position(extents.width() as f64, extents.height() as f64);
+        snapshot.save();
+        snapshot.translate(&graphene::Point::new(x as f32, y as f32));
+        snapshot.append_layout(&layout, &color);
+        snapshot.restore();
+    }
+    fn texture(&self, piece: &Piece) -> Option<gdk::Texture> {
+        let file = piece_file(piece)?;
+        self.imp()
+            .textures
+            .borrow_mut()
+            .entry(file)
+            .or_insert_with(|| match gdk::Texture::from_filename(file) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    println!("{}: {}", file, e);
+                    None
+                }
+            })
+            .clone()
+    }
+}
+
+/// a red glow fading out from the middle of the square
+fn draw_check_glow(snapshot: &gtk::Snapshot, rect: &graphene::Rect) {
+    let center = rect.center();
+    let radius = rect.width() * 0.7;
+    let glow = rgba(CHECK_COLOR);
+    let mut faded = glow;
+    faded.set_alpha(0.0);
+    snapshot.append_radial_gradient(
+        rect,
+        &center,
+        radius,
+        radius,
+        0.0,
+        1.0,
+        &[
+            gsk::ColorStop::new(0.0, glow),
+            gsk::ColorStop::new(0.4, glow),
+            gsk::ColorStop::new(1.0, faded),
+        ],
+    );
+}
+/// a dot in the middle of a square the selected piece can move to, or a ring
+/// around its edge if the move captures
+fn draw_move_hint(snapshot: &gtk::Snapshot, rect: &graphene::Rect, captures: bool) {
+    let color = rgba(MOVE_HINT_COLOR);
+    let side = rect.width();
+    if captures {
+        let ring = gsk::RoundedRect::from_rect(*rect, side / 2.0);
+        let width = side * 0.08;
+        snapshot.append_border(&ring, &[width; 4], &[color; 4]);
+    } else {
+        let radius = side * 0.15;
+        let center = rect.center();
+        let dot = graphene::Rect::new(
+            center.x() - radius,
+            center.y() - radius,
+            radius * 2.0,
+            radius * 2.0,
+        );
+        snapshot.push_rounded_clip(&gsk::RoundedRect::from_rect(dot, radius));
+        snapshot.append_color(&color, &dot);
+        snapshot.pop();
+    }
+}
+fn square_color((row, col): (usize, usize)) -> &'static str {
+    if (row + col) % 2 == 1 {
+        DARK_SQUARE_COLOR
+    } else {
+        LIGHT_SQUARE_COLOR
+    }
+}
+/// the color of the other squares, for writing on this one
+fn contrasting_color(square: (usize, usize)) -> &'static str {
+    if square_color(square) == DARK_SQUARE_COLOR {
+        LIGHT_SQUARE_COLOR
+    } else {
+        DARK_SQUARE_COLOR
+    }
+}
+fn rgba(color: &str) -> gdk::RGBA {
+    gdk::RGBA::parse(color).expect("Failed to parse a board color")
+}
+fn square_index((row, col): (usize, usize)) -> u32 {
+    (row * COLS + col) as u32
+}
+fn index_square(index: u32) -> (usize, usize) {
+    (index as usize / COLS, index as usize % COLS)
+}
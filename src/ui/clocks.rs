@@ -0,0 +1,56 @@
+use crate::clock::{format_clock_time, TimeControl};
+use crate::consts::CLOCK_CSS;
+use gtk::prelude::*;
+use gtk::Label;
+use std::time::Duration;
+
+/// what the PGN TimeControl tag writes for a game without a clock
+pub const NO_CLOCK: &str = "-";
+/// the time controls offered in the preferences menu, with their TimeControl tags
+pub const TIME_CONTROLS: [(&str, &str); 7] = [
+    ("No clock", NO_CLOCK),
+    ("3 min + 2 s", "180+2"),
+    ("5 min + 3 s", "300+3"),
+    ("10 min", "600"),
+    ("15 min + 10 s", "900+10"),
+    ("5 min, 5 s delay", "300d5"),
+    (
+        "40 moves in 90 min, then 30 min + 30 s",
+        "40/5400+30:1800+30",
+    ),
+];
+/// reads a TimeControl tag, where `Some(None)` is playing without a clock
+pub fn parse_time_control(tag: &str) -> Option<Option<TimeControl>> {
+    if tag == NO_CLOCK {
+        return Some(None);
+    }
+    tag.parse().ok().map(Some)
+}
+/// the TimeControl tag for `control`, `NO_CLOCK` for none
+pub fn time_control_tag(control: Option<&TimeControl>) -> String {
+    control.map_or_else(|| NO_CLOCK.to_string(), TimeControl::to_string)
+}
+/// builds one player's clock display
+pub fn build_clock_label() -> Label {
+    let provider = gtk::CssProvider::new();
+    provider.load_from_data(CLOCK_CSS);
+    if let Some(display) = gtk::gdk::Display::default() {
+        gtk::style_context_add_provider_for_display(
+            &display,
+            &provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+    }
+    let label = Label::new(None);
+    label.add_css_class("clock");
+    label.set_halign(gtk::Align::End);
+    label
+}
+pub fn update_clock_label(label: &Label, remaining: Duration, running: bool) {
+    label.set_text(&format_clock_time(remaining));
+    if running {
+        label.add_css_class("clock-running");
+    } else {
+        label.remove_css_class("clock-running");
+    }
+}
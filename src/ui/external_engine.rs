@@ -0,0 +1,165 @@
+use crate::board::piece::PieceColor;
+use crate::board::Board;
+use crate::consts::{ENGINES_CONFIG, ENGINE_HANDSHAKE_TIMEOUT_MS};
+use crate::engine::Score;
+use crate::uci::{EngineConfig, GoCommand, UciEngine, UciError, UciInfo, UciMessage};
+use gtk::{gio, glib};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// prefix of the keys in an engine's group that are passed on with `setoption`
+const OPTION_KEY_PREFIX: &str = "Option.";
+
+/// reads the external engines from `ENGINES_CONFIG` in the user's config directory,
+/// a key file with one group per engine:
+///
+/// ```ini
+/// [Stockfish]
+/// Path=/usr/bin/stockfish
+/// Arguments=
+/// Option.Threads=4
+/// Option.Move Overhead=100
+/// ```
+///
+/// a missing file means no engines, broken groups are skipped with a message
+pub(super) fn load_engine_configs() -> Vec<EngineConfig> {
+    let path = glib::user_config_dir().join(ENGINES_CONFIG);
+    let key_file = glib::KeyFile::new();
+    if key_file
+        .load_from_file(&path, glib::KeyFileFlags::NONE)
+        .is_err()
+    {
+        return vec![];
+    }
+    let mut configs = vec![];
+    for group in key_file.groups().iter() {
+        let name = group.as_str();
+        let Ok(engine_path) = key_file.string(name, "Path") else {
+            println!("{}: engine {} has no Path", path.display(), name);
+            continue;
+        };
+        let args = key_file
+            .string_list(name, "Arguments")
+            .map(|args| args.iter().map(|arg| arg.as_str().to_string()).collect())
+            .unwrap_or_default();
+        let mut options = vec![];
+        for key in key_file.keys(name).iter().flat_map(|keys| keys.iter()) {
+            if let Some(option) = key.as_str().strip_prefix(OPTION_KEY_PREFIX) {
+                if let Ok(value) = key_file.string(name, key.as_str()) {
+                    options.push((option.to_string(), value.to_string()));
+                }
+            }
+        }
+        configs.push(EngineConfig {
+            name: name.to_string(),
+            path: PathBuf::from(engine_path.as_str()),
+            args,
+            options,
+        });
+    }
+    configs
+}
+/// starts the engine off the main thread, then hands it to `on_started` on it
+pub(super) fn start_engine<F: FnOnce(Result<UciEngine, UciError>) + 'static>(
+    config: EngineConfig,
+    on_started: F,
+) {
+    glib::spawn_future_local(async move {
+        let timeout = Duration::from_millis(ENGINE_HANDSHAKE_TIMEOUT_MS);
+        let started = gio::spawn_blocking(move || UciEngine::start(&config, timeout)).await;
+        on_started(started.unwrap_or(Err(UciError::Exited)));
+    });
+}
+
+/// An external engine the window talks to, keeping track of which of its answers
+/// still matter.
+pub(super) struct ExternalEngine {
+    engine: UciEngine,
+    searching: bool,
+    /// bestmoves still to come from searches that were stopped, to be dropped
+    stale_bestmoves: u32,
+}
+impl ExternalEngine {
+    pub(super) fn new(engine: UciEngine) -> ExternalEngine {
+        ExternalEngine {
+            engine,
+            searching: false,
+            stale_bestmoves: 0,
+        }
+    }
+    pub(super) fn name(&self) -> &str {
+        self.engine.name()
+    }
+    pub(super) fn is_searching(&self) -> bool {
+        self.searching
+    }
+    /// abandons the current search, if any, and searches the board
+    pub(super) fn search(&mut self, board: &Board, go: &GoCommand) -> Result<(), UciError> {
+        self.stop()?;
+        self.engine.set_position(board)?;
+        self.engine.go(go)?;
+        self.searching = true;
+        Ok(())
+    }
+    /// abandons the current search, its bestmove will not be passed on
+    pub(super) fn stop(&mut self) -> Result<(), UciError> {
+        if self.searching {
+            self.engine.stop()?;
+            self.stale_bestmoves += 1;
+            self.searching = false;
+        }
+        Ok(())
+    }
+    /// every message that arrived since the last poll, without the output of
+    /// abandoned searches
+    pub(super) fn poll(&mut self) -> Result<Vec<UciMessage>, UciError> {
+        let mut messages = vec![];
+        while let Some(message) = self.engine.try_recv()? {
+            if self.stale_bestmoves > 0 {
+                if let UciMessage::BestMove { .. } = message {
+                    self.stale_bestmoves -= 1;
+                }
+                continue;
+            }
+            if let UciMessage::BestMove { .. } = message {
+                self.searching = false;
+            }
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+}
+/// one line of analysis for the board the engine was given, e.g.
+/// "+0.35 depth 18: 12. Nf3 Nc6 13. d4", the score from white's point of view
+pub(super) fn format_analysis(board: &Board, info: &UciInfo) -> Option<String> {
+    let score = info.score?;
+    let white_score = match (score, board.turn_player) {
+        (_, PieceColor::White) => score,
+        (Score::Centipawns(cp), _) => Score::Centipawns(-cp),
+        (Score::Mate(moves), _) => Score::Mate(-moves),
+    };
+    let mut text = match white_score {
+        Score::Centipawns(cp) => format!("{:+.2}", cp as f64 / 100.0),
+        Score::Mate(moves) => format!("#{}", moves),
+    };
+    if let Some(depth) = info.depth {
+        text.push_str(&format!(" depth {}", depth));
+    }
+    text.push(':');
+    let mut board = board.clone();
+    for (i, uci) in info.pv.iter().enumerate() {
+        let Some(mv) = board.parse_uci_move(uci) else {
+            break;
+        };
+        let white_to_move = board.turn_player == PieceColor::White;
+        if white_to_move {
+            text.push_str(&format!(" {}.", board.fullmove_number()));
+        } else if i == 0 {
+            text.push_str(&format!(" {}...", board.fullmove_number()));
+        }
+        text.push(' ');
+        text.push_str(&board.move_to_san(mv.from, mv.to, mv.promotion));
+        board.make_move(mv);
+    }
+    Some(text)
+}
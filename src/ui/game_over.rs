@@ -0,0 +1,177 @@
+use crate::board::piece::PieceColor;
+use crate::board::status::GameResult;
+use crate::board::Board;
+use crate::pgn::{parse_pgn, PgnGame};
+use glib::clone;
+use gtk::glib;
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What the players want after a game, picked in the result dialog.
+#[derive(Clone, Copy)]
+pub(super) enum GameOverChoice {
+    NewGame,
+    /// a new game with the sides swapped
+    Rematch,
+}
+
+/// whose turn it is and whether they are in check, or how the game ended
+pub(super) fn status_text(board: &Board, result: Option<GameResult>) -> String {
+    if let Some(result) = result {
+        return result.to_string();
+    }
+    let side = color_name(board.turn_player);
+    if board.is_in_check() {
+        format!("{} to move, in check", side)
+    } else {
+        format!("{} to move", side)
+    }
+}
+/// the moves played on the board as a game dated today, ending with `result`
+pub(super) fn game_pgn(board: &Board, result: Option<GameResult>) -> PgnGame {
+    let mut game = PgnGame::from_board(board);
+    if let Some(date) = glib::DateTime::now_local()
+        .ok()
+        .and_then(|now| now.format("%Y.%m.%d").ok())
+    {
+        game.set_tag("Date", &date);
+    }
+    game.set_result(result.map_or("*", |result| result.pgn_result()));
+    game
+}
+/// shows who won, why and after how many moves in a modal dialog, with buttons to
+/// play again or save the game as PGN. `on_choice` is called with what the players
+/// picked, closing the dialog leaves the board as it is
+pub(super) fn show_result_dialog<F: Fn(GameOverChoice) + 'static>(
+    parent: Option<&gtk::Window>,
+    board: &Board,
+    result: GameResult,
+    on_choice: F,
+) {
+    let on_choice = Rc::new(on_choice);
+    let dialog = gtk::Window::builder()
+        .title("Game over")
+        .modal(true)
+        .resizable(false)
+        .build();
+    dialog.set_transient_for(parent);
+    let heading = match result.winner {
+        Some(winner) => format!("{} wins", color_name(winner)),
+        None => "Draw".to_string(),
+    };
+    let heading = gtk::Label::new(Some(&heading));
+    heading.add_css_class("title-2");
+    let moves = board.history().len().div_ceil(2);
+    let detail = format!(
+        "By {} after {} move{}",
+        result.reason,
+        moves,
+        if moves == 1 { "" } else { "s" }
+    );
+    let detail = gtk::Label::new(Some(&detail));
+
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    buttons.set_halign(gtk::Align::Center);
+    for (label, choice) in [
+        ("New game", GameOverChoice::NewGame),
+        ("Rematch", GameOverChoice::Rematch),
+    ] {
+        let button = gtk::Button::with_label(label);
+        button.connect_clicked(clone!(
+            #[strong]
+            on_choice,
+            #[weak]
+            dialog,
+            move |_| {
+                dialog.close();
+                on_choice(choice);
+            }
+        ));
+        buttons.append(&button);
+    }
+    let pgn = game_pgn(board, Some(result));
+    let save_button = gtk::Button::with_label("Save PGN");
+    save_button.connect_clicked(clone!(
+        #[weak]
+        dialog,
+        move |_| save_pgn(&dialog, &pgn)
+    ));
+    buttons.append(&save_button);
+
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 12);
+    content.set_margin_top(18);
+    content.set_margin_bottom(18);
+    content.set_margin_start(18);
+    content.set_margin_end(18);
+    content.append(&heading);
+    content.append(&detail);
+    content.append(&buttons);
+    dialog.set_child(Some(&content));
+    dialog.present();
+}
+/// asks where to save the game and writes it there
+fn save_pgn(parent: &gtk::Window, game: &PgnGame) {
+    let pgn = match game.to_pgn() {
+        Ok(pgn) => pgn,
+        Err(e) => {
+            println!("cannot save the game: {}", e);
+            return;
+        }
+    };
+    let chooser = gtk::FileChooserNative::new(
+        Some("Save game"),
+        Some(parent),
+        gtk::FileChooserAction::Save,
+        Some("Save"),
+        Some("Cancel"),
+    );
+    chooser.set_current_name("game.pgn");
+    // the chooser goes away once nothing holds it, so it holds itself until it answers
+    let keep_alive = RefCell::new(Some(chooser.clone()));
+    chooser.connect_response(move |chooser, response| {
+        if response == gtk::ResponseType::Accept {
+            if let Some(path) = chooser.file().and_then(|file| file.path()) {
+                if let Err(e) = std::fs::write(&path, &pgn) {
+                    println!("{}: {}", path.display(), e);
+                }
+            }
+        }
+        keep_alive.take();
+    });
+    chooser.show();
+}
+/// asks for a PGN file and calls `on_open` with the first game in it
+pub(super) fn open_pgn<F: Fn(PgnGame) + 'static>(parent: Option<&gtk::Window>, on_open: F) {
+    let chooser = gtk::FileChooserNative::new(
+        Some("Open game"),
+        parent,
+        gtk::FileChooserAction::Open,
+        Some("Open"),
+        Some("Cancel"),
+    );
+    // the chooser goes away once nothing holds it, so it holds itself until it answers
+    let keep_alive = RefCell::new(Some(chooser.clone()));
+    chooser.connect_response(move |chooser, response| {
+        if response == gtk::ResponseType::Accept {
+            if let Some(path) = chooser.file().and_then(|file| file.path()) {
+                let games = std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| parse_pgn(&text).map_err(|e| e.to_string()));
+                match games.map(|games| games.into_iter().next()) {
+                    Ok(Some(game)) => on_open(game),
+                    Ok(None) => println!("{}: no game in the file", path.display()),
+                    Err(e) => println!("{}: {}", path.display(), e),
+                }
+            }
+        }
+        keep_alive.take();
+    });
+    chooser.show();
+}
+fn color_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::White => "White",
+        PieceColor::Black => "Black",
+    }
+}
@@ -1,49 +1,25 @@
 use crate::board::piece::{Piece, PieceColor};
 use gtk::Image;
+/// the picture of the piece, `None` for an empty square
+pub fn piece_file(piece: &Piece) -> Option<&'static str> {
+    let white = piece.color() == Some(PieceColor::White);
+    let file = match piece {
+        Piece::Pawn(_) if white => "./resources/Chess_plt60.png",
+        Piece::Pawn(_) => "./resources/Chess_pdt60.png",
+        Piece::Knight(_) if white => "./resources/Chess_nlt60.png",
+        Piece::Knight(_) => "./resources/Chess_ndt60.png",
+        Piece::Bishop(_) if white => "./resources/Chess_blt60.png",
+        Piece::Bishop(_) => "./resources/Chess_bdt60.png",
+        Piece::Rook(_) if white => "./resources/Chess_rlt60.png",
+        Piece::Rook(_) => "./resources/Chess_rdt60.png",
+        Piece::Queen(_) if white => "./resources/Chess_qlt60.png",
+        Piece::Queen(_) => "./resources/Chess_qdt60.png",
+        Piece::King(_) if white => "./resources/Chess_klt60.png",
+        Piece::King(_) => "./resources/Chess_kdt60.png",
+        Piece::Empty(_) => return None,
+    };
+    Some(file)
+}
 pub fn get_image(piece: &Piece) -> Option<Image> {
-    match piece {
-        Piece::Pawn(p) => {
-            if p.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_plt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_pdt60.png"))
-            }
-        }
-        Piece::Knight(k) => {
-            if k.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_nlt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_ndt60.png"))
-            }
-        }
-        Piece::Bishop(b) => {
-            if b.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_blt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_bdt60.png"))
-            }
-        }
-        Piece::Rook(r) => {
-            if r.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_rlt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_rdt60.png"))
-            }
-        }
-        Piece::Queen(q) => {
-            if q.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_qlt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_qdt60.png"))
-            }
-        }
-        Piece::King(k) => {
-            if k.color == PieceColor::White {
-                Some(Image::from_file("./resources/Chess_klt60.png"))
-            } else {
-                Some(Image::from_file("./resources/Chess_kdt60.png"))
-            }
-        }
-        Piece::Empty(_) => None,
-    }
+    piece_file(piece).map(Image::from_file)
 }
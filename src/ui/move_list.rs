@@ -0,0 +1,50 @@
+use crate::consts::CURRENT_MOVE_CSS;
+use gtk::prelude::*;
+use gtk::{Button, Grid, Label};
+use std::rc::Rc;
+
+/// builds the empty move list. every row is a move number, white's move and black's move
+pub fn build_move_list() -> Grid {
+    let provider = gtk::CssProvider::new();
+    provider.load_from_data(CURRENT_MOVE_CSS);
+    if let Some(display) = gtk::gdk::Display::default() {
+        gtk::style_context_add_provider_for_display(
+            &display,
+            &provider,
+            gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        );
+    }
+    let move_list = Grid::new();
+    move_list.set_column_spacing(4);
+    move_list.set_row_spacing(2);
+    move_list.set_margin_start(6);
+    move_list.set_margin_end(6);
+    move_list.set_margin_top(6);
+    move_list.set_margin_bottom(6);
+    move_list
+}
+
+/// fills the list with the moves of the line in `sans`, marking the one that led to
+/// `current_ply`. clicking a move calls `on_click` with the ply reached after it
+pub fn refresh(move_list: &Grid, sans: &[String], current_ply: usize, on_click: Rc<dyn Fn(usize)>) {
+    while let Some(child) = move_list.first_child() {
+        move_list.remove(&child);
+    }
+    for (index, san) in sans.iter().enumerate() {
+        let row = (index / 2) as i32;
+        if index % 2 == 0 {
+            let number = Label::new(Some(&format!("{}.", index / 2 + 1)));
+            number.set_xalign(1.0);
+            move_list.attach(&number, 0, row, 1, 1);
+        }
+        let ply = index + 1;
+        let move_button = Button::with_label(san);
+        move_button.set_has_frame(false);
+        if ply == current_ply {
+            move_button.add_css_class("current-move");
+        }
+        let on_click = on_click.clone();
+        move_button.connect_clicked(move |_| on_click(ply));
+        move_list.attach(&move_button, (index % 2) as i32 + 1, row, 1, 1);
+    }
+}
@@ -0,0 +1,39 @@
+use crate::board::Board;
+use crate::book::Book;
+use crate::consts::OPENING_BOOK;
+use gtk::glib;
+
+/// reads the Polyglot book at `OPENING_BOOK` in the user's config directory.
+/// a missing file means no book, a broken one is reported and ignored
+pub(super) fn load_book() -> Option<Book> {
+    let path = glib::user_config_dir().join(OPENING_BOOK);
+    if !path.exists() {
+        return None;
+    }
+    match Book::open(&path) {
+        Ok(book) => Some(book),
+        Err(e) => {
+            println!("{}: {}", path.display(), e);
+            None
+        }
+    }
+}
+/// the book moves for the board with their share of the weight, heaviest first,
+/// e.g. "Book: e4 45%, d4 35%, Nf3 12%"
+pub(super) fn format_book_moves(board: &Board, book: &Book) -> String {
+    let entries = book.entries(board);
+    if entries.is_empty() {
+        return "Out of book".to_string();
+    }
+    let total: u32 = entries.iter().map(|entry| entry.weight as u32).sum();
+    let mut board = board.clone();
+    let moves: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            let san = board.move_to_san(entry.mv.from, entry.mv.to, entry.mv.promotion);
+            let share = entry.weight as u32 * 100 / total.max(1);
+            format!("{} {}%", san, share)
+        })
+        .collect();
+    format!("Book: {}", moves.join(", "))
+}
@@ -0,0 +1,91 @@
+use super::board_view::Coordinates;
+use super::clocks::{parse_time_control, time_control_tag};
+use crate::clock::TimeControl;
+use crate::consts::{DEFAULT_TIME_CONTROL, PREFERENCES};
+use gtk::glib;
+
+const BOARD_GROUP: &str = "Board";
+const COORDINATES_KEY: &str = "Coordinates";
+const GAME_GROUP: &str = "Game";
+const TIME_CONTROL_KEY: &str = "TimeControl";
+
+/// Settings kept from one session to the next.
+#[derive(Clone, Debug)]
+pub(super) struct Preferences {
+    pub(super) coordinates: Coordinates,
+    /// the clock new games are played with, `None` to play without one
+    pub(super) time_control: Option<TimeControl>,
+}
+impl Default for Preferences {
+    fn default() -> Self {
+        Preferences {
+            coordinates: Coordinates::default(),
+            time_control: Some(
+                DEFAULT_TIME_CONTROL
+                    .parse()
+                    .expect("Failed to parse the default time control"),
+            ),
+        }
+    }
+}
+impl Preferences {
+    /// reads the preferences from `PREFERENCES` in the user's config directory,
+    /// a key file like:
+    ///
+    /// ```ini
+    /// [Board]
+    /// Coordinates=margin
+    ///
+    /// [Game]
+    /// TimeControl=40/5400+30:1800+30
+    /// ```
+    ///
+    /// where the time control is a PGN TimeControl tag, or `-` for no clock.
+    /// anything missing or not understood keeps its default
+    pub(super) fn load() -> Preferences {
+        let mut preferences = Preferences::default();
+        let key_file = glib::KeyFile::new();
+        let path = glib::user_config_dir().join(PREFERENCES);
+        if key_file
+            .load_from_file(&path, glib::KeyFileFlags::NONE)
+            .is_err()
+        {
+            return preferences;
+        }
+        if let Some(coordinates) = key_file
+            .string(BOARD_GROUP, COORDINATES_KEY)
+            .ok()
+            .and_then(|name| Coordinates::from_name(&name))
+        {
+            preferences.coordinates = coordinates;
+        }
+        if let Some(time_control) = key_file
+            .string(GAME_GROUP, TIME_CONTROL_KEY)
+            .ok()
+            .and_then(|tag| parse_time_control(&tag))
+        {
+            preferences.time_control = time_control;
+        }
+        preferences
+    }
+    /// writes the preferences back, reporting a failure
+    pub(super) fn save(&self) {
+        let path = glib::user_config_dir().join(PREFERENCES);
+        let key_file = glib::KeyFile::new();
+        key_file.set_string(BOARD_GROUP, COORDINATES_KEY, self.coordinates.name());
+        key_file.set_string(
+            GAME_GROUP,
+            TIME_CONTROL_KEY,
+            &time_control_tag(self.time_control.as_ref()),
+        );
+        if let Some(dir) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                println!("{}: {}", dir.display(), e);
+                return;
+            }
+        }
+        if let Err(e) = key_file.save_to_file(&path) {
+            println!("{}: {}", path.display(), e);
+        }
+    }
+}
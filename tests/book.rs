@@ -0,0 +1,108 @@
+mod common;
+
+use common::play;
+use xp_chess::board::{Board, MoveType};
+use xp_chess::book::{Book, BookBuilder, BookError};
+use xp_chess::pgn::parse_pgn;
+
+const GAMES: &str = "
+[Result \"*\"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. O-O *
+
+[Result \"*\"]
+
+1. e4 e5 2. Nf3 Nc6 *
+
+[Result \"*\"]
+
+1. e4 c5 *
+
+[Result \"*\"]
+
+1. d4 d5 *
+";
+
+fn build(max_ply: usize, min_count: u32) -> Vec<u8> {
+    let mut builder = BookBuilder::new(max_ply, min_count);
+    for game in parse_pgn(GAMES).unwrap() {
+        builder.add_game(&game).unwrap();
+    }
+    builder.to_bytes()
+}
+/// the book moves for the board in UCI notation, with their weights
+fn moves(book: &Book, board: &Board) -> Vec<(String, u16)> {
+    book.entries(board)
+        .iter()
+        .map(|entry| (entry.mv.to_uci(), entry.weight))
+        .collect()
+}
+
+#[test]
+fn entries_are_written_in_polyglot_format() {
+    let bytes = build(20, 1);
+    let start: Vec<&[u8]> = bytes
+        .chunks(16)
+        .filter(|entry| entry[..8] == 0x463b96181691fc9cu64.to_be_bytes())
+        .collect();
+    // heaviest move first: e2e4, played in three games, then d2d4
+    assert_eq!(start.len(), 2);
+    assert_eq!(start[0][8..], [0x03, 0x1c, 0, 3, 0, 0, 0, 0]);
+    assert_eq!(start[1][8..], [0x02, 0xdb, 0, 1, 0, 0, 0, 0]);
+    // castling is stored as the king taking its own rook, e1h1
+    assert!(bytes.chunks(16).any(|entry| entry[8..10] == [0x01, 0x07]));
+}
+#[test]
+fn book_moves_are_looked_up_with_their_weights() {
+    let book = Book::from_bytes(&build(20, 1)).unwrap();
+    let mut board = Board::default();
+    assert_eq!(
+        moves(&book, &board),
+        [("e2e4".to_string(), 3), ("d2d4".to_string(), 1)]
+    );
+    play(&mut board, &["e2e4"]);
+    assert_eq!(
+        moves(&book, &board),
+        [("e7e5".to_string(), 2), ("c7c5".to_string(), 1)]
+    );
+    play(&mut board, &["e7e5", "g1f3", "b8c6", "f1c4", "f8c5"]);
+    let entries = book.entries(&board);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].mv.to_uci(), "e1g1");
+    assert_eq!(entries[0].mv.kind, MoveType::Castle);
+    play(&mut board, &["e1g1"]);
+    assert!(book.entries(&board).is_empty());
+}
+#[test]
+fn depth_and_frequency_filters_leave_moves_out() {
+    let frequent = Book::from_bytes(&build(20, 2)).unwrap();
+    let mut board = Board::default();
+    assert_eq!(moves(&frequent, &board), [("e2e4".to_string(), 3)]);
+    play(&mut board, &["e2e4"]);
+    assert_eq!(moves(&frequent, &board), [("e7e5".to_string(), 2)]);
+
+    let shallow = Book::from_bytes(&build(1, 1)).unwrap();
+    assert_eq!(shallow.len(), 2);
+    assert!(shallow.entries(&board).is_empty());
+}
+#[test]
+fn weighted_move_follows_the_weights() {
+    let book = Book::from_bytes(&build(20, 1)).unwrap();
+    let board = Board::default();
+    let picked = |roll| book.weighted_move(&board, roll).unwrap().to_uci();
+    assert_eq!(picked(0), "e2e4");
+    assert_eq!(picked(2), "e2e4");
+    assert_eq!(picked(3), "d2d4");
+    assert_eq!(picked(4), "e2e4");
+    let mut out_of_book = Board::default();
+    play(&mut out_of_book, &["g1f3"]);
+    assert_eq!(book.pick_move(&out_of_book), None);
+}
+#[test]
+fn truncated_books_are_rejected() {
+    let bytes = build(20, 1);
+    assert!(matches!(
+        Book::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(BookError::InvalidLength(_))
+    ));
+}
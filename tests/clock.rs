@@ -0,0 +1,217 @@
+//! The chess clock, driven by `ManualTime` so no test has to wait.
+use std::time::Duration;
+use xp_chess::board::piece::PieceColor::{Black, White};
+use xp_chess::clock::{
+    format_clock_time, Clock, Increment, ManualTime, TimeControl, TimeControlStage,
+};
+
+fn secs(seconds: u64) -> Duration {
+    Duration::from_secs(seconds)
+}
+/// a clock for `control` that has started white's first move
+fn started(control: TimeControl) -> (Clock<ManualTime>, ManualTime) {
+    let time = ManualTime::new();
+    let mut clock = Clock::new(control, time.clone());
+    clock.start(White);
+    (clock, time)
+}
+
+#[test]
+fn nothing_runs_before_the_start() {
+    let time = ManualTime::new();
+    let mut clock = Clock::new(TimeControl::sudden_death(secs(60)), time.clone());
+    time.advance(secs(30));
+    clock.press();
+    assert_eq!(clock.running(), None);
+    assert_eq!(clock.remaining(White), secs(60));
+    assert_eq!(clock.remaining(Black), secs(60));
+}
+
+#[test]
+fn sudden_death() {
+    let (mut clock, time) = started(TimeControl::sudden_death(secs(60)));
+    time.advance(secs(10));
+    assert_eq!(clock.remaining(White), secs(50));
+    clock.press();
+    assert_eq!(clock.running(), Some(Black));
+    assert_eq!(clock.remaining(White), secs(50));
+    time.advance(secs(5));
+    assert_eq!(clock.remaining(Black), secs(55));
+    assert_eq!(clock.remaining(White), secs(50));
+}
+
+#[test]
+fn fischer_increment() {
+    let (mut clock, time) = started(TimeControl::fischer(secs(60), secs(2)));
+    time.advance(secs(10));
+    clock.press();
+    assert_eq!(clock.remaining(White), secs(52));
+    // the increment is added even for an instant move
+    clock.press();
+    assert_eq!(clock.remaining(Black), secs(62));
+}
+
+#[test]
+fn bronstein_delay() {
+    let (mut clock, time) = started(TimeControl::bronstein(secs(60), secs(5)));
+    // a move within the delay costs nothing
+    time.advance(secs(3));
+    clock.press();
+    assert_eq!(clock.remaining(White), secs(60));
+    // a longer one only gets the delay back
+    time.advance(secs(8));
+    clock.press();
+    assert_eq!(clock.remaining(Black), secs(57));
+}
+
+#[test]
+fn multi_stage_rollover() {
+    // two moves in 100 s, then 50 s more for the rest, with 1 s per move throughout
+    let (mut clock, time) = started("2/100+1:50+1".parse().unwrap());
+    for _ in 0..2 {
+        time.advance(secs(10));
+        clock.press();
+        time.advance(secs(1));
+        clock.press();
+    }
+    assert_eq!(clock.remaining(White), secs(100 - 20 + 2 + 50));
+    assert_eq!(clock.remaining(Black), secs(100 - 2 + 2 + 50));
+    // the last stage has no move count, so nothing more is added
+    for _ in 0..3 {
+        time.advance(secs(10));
+        clock.press();
+        clock.press();
+    }
+    assert_eq!(clock.remaining(White), secs(132 - 30 + 3));
+}
+
+#[test]
+fn a_last_stage_with_moves_repeats() {
+    let (mut clock, time) = started(TimeControl::multi_stage(vec![TimeControlStage {
+        moves: Some(1),
+        time: secs(10),
+        increment: Increment::None,
+    }]));
+    time.advance(secs(4));
+    clock.press();
+    assert_eq!(clock.remaining(White), secs(16));
+    clock.press();
+    time.advance(secs(6));
+    clock.press();
+    assert_eq!(clock.remaining(White), secs(20));
+}
+
+#[test]
+fn a_stage_for_zero_moves_lasts_the_rest_of_the_game() {
+    // only reachable through the public field, `multi_stage` and parsing refuse it
+    let control = TimeControl {
+        stages: vec![TimeControlStage {
+            moves: Some(0),
+            time: secs(10),
+            increment: Increment::Fischer(secs(1)),
+        }],
+    };
+    let (mut clock, time) = started(control);
+    time.advance(secs(4));
+    clock.press();
+    assert_eq!(clock.remaining(White), secs(7));
+    assert!("0/60".parse::<TimeControl>().is_err());
+}
+
+#[test]
+#[should_panic]
+fn multi_stage_refuses_a_stage_for_zero_moves() {
+    TimeControl::multi_stage(vec![TimeControlStage {
+        moves: Some(0),
+        time: secs(10),
+        increment: Increment::None,
+    }]);
+}
+
+#[test]
+fn flag_fall() {
+    let (mut clock, time) = started(TimeControl::fischer(secs(10), secs(5)));
+    time.advance(secs(9));
+    assert_eq!(clock.check_flag(), None);
+    time.advance(secs(1));
+    assert_eq!(clock.check_flag(), Some(White));
+    assert_eq!(clock.running(), None);
+    assert_eq!(clock.remaining(White), Duration::ZERO);
+    // a fallen flag stays fallen, the increment comes too late
+    clock.press();
+    clock.start(Black);
+    assert_eq!(clock.check_flag(), Some(White));
+    assert_eq!(clock.running(), None);
+}
+
+#[test]
+fn pressing_after_the_time_is_up_flags() {
+    let (mut clock, time) = started(TimeControl::fischer(secs(10), secs(5)));
+    time.advance(secs(12));
+    clock.press();
+    assert_eq!(clock.check_flag(), Some(White));
+    assert_eq!(clock.remaining(White), Duration::ZERO);
+    assert_eq!(clock.remaining(Black), secs(10));
+}
+
+#[test]
+fn stop_keeps_the_time_used() {
+    let (mut clock, time) = started(TimeControl::sudden_death(secs(60)));
+    time.advance(secs(10));
+    clock.stop();
+    time.advance(secs(100));
+    assert_eq!(clock.remaining(White), secs(50));
+    assert_eq!(clock.check_flag(), None);
+    clock.start(White);
+    time.advance(secs(5));
+    assert_eq!(clock.remaining(White), secs(45));
+}
+
+#[test]
+fn restore_takes_the_clock_back() {
+    let (mut clock, time) = started(TimeControl::fischer(secs(10), secs(1)));
+    time.advance(secs(3));
+    clock.press();
+    let after_first_move = clock.state();
+    time.advance(secs(20));
+    assert_eq!(clock.check_flag(), Some(Black));
+    clock.restore(after_first_move);
+    assert_eq!(clock.check_flag(), None);
+    assert_eq!(clock.running(), None);
+    assert_eq!(clock.remaining(White), secs(8));
+    assert_eq!(clock.remaining(Black), secs(10));
+    clock.start(Black);
+    time.advance(secs(2));
+    clock.press();
+    assert_eq!(clock.remaining(Black), secs(9));
+}
+
+#[test]
+fn reads_and_writes_the_pgn_time_control_tag() {
+    for text in [
+        "300",
+        "300+3",
+        "300d5",
+        "40/5400+30:1800+30",
+        "40/7200:20/3600:900",
+    ] {
+        let control: TimeControl = text.parse().unwrap();
+        assert_eq!(control.to_string(), text);
+    }
+    assert_eq!(
+        "300+3".parse::<TimeControl>(),
+        Ok(TimeControl::fischer(secs(300), secs(3)))
+    );
+    for text in ["", "-", "abc", "5+", "40/", "x/300", "300:", "1/2/3"] {
+        assert!(text.parse::<TimeControl>().is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn formats_clock_times() {
+    assert_eq!(format_clock_time(secs(3725)), "1:02:05");
+    assert_eq!(format_clock_time(secs(300)), "5:00");
+    assert_eq!(format_clock_time(secs(10)), "0:10");
+    assert_eq!(format_clock_time(Duration::from_millis(9_450)), "9.4");
+    assert_eq!(format_clock_time(Duration::ZERO), "0.0");
+}
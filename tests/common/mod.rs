@@ -0,0 +1,16 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+use xp_chess::board::Board;
+
+pub fn board(fen: &str) -> Board {
+    Board::from_fen(fen).unwrap()
+}
+/// plays the moves given in UCI notation
+pub fn play(board: &mut Board, moves: &[&str]) {
+    for uci in moves {
+        let mv = board
+            .parse_uci_move(uci)
+            .unwrap_or_else(|| panic!("{} is not legal", uci));
+        board.make_move(mv);
+    }
+}
@@ -0,0 +1,33 @@
+#!/bin/sh
+# A stand-in UCI engine for the client tests. It answers the handshake, echoes
+# every setoption and position back as an info string, and replies to go with a
+# fixed search. "go infinite" holds the bestmove back until stop.
+infinite=0
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Fake Engine"
+            echo "id author xp-chess tests"
+            echo "option name Skill type spin default 1 min 0 max 20"
+            echo "uciok"
+            ;;
+        isready) echo "readyok" ;;
+        setoption*|position*) echo "info string $line" ;;
+        "go infinite")
+            echo "info depth 1 score cp 13 pv d2d4"
+            infinite=1
+            ;;
+        go*)
+            echo "info depth 3 seldepth 5 score cp 25 nodes 1234 nps 1000 time 12 pv e2e4 e7e5"
+            echo "info depth 4 score mate -3 lowerbound nodes 2000 pv e2e4"
+            echo "bestmove e2e4 ponder e7e5"
+            ;;
+        stop)
+            if [ "$infinite" = 1 ]; then
+                echo "bestmove d2d4"
+                infinite=0
+            fi
+            ;;
+        quit) exit 0 ;;
+    esac
+done
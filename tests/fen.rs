@@ -0,0 +1,181 @@
+//! Reading and writing positions in Forsyth-Edwards Notation, and the
+//! positions `Board::from_fen` refuses.
+use xp_chess::board::fen::{FenError, STARTING_FEN};
+use xp_chess::board::piece::PieceColor;
+use xp_chess::board::{parse_square, Board};
+
+fn parse_error(fen: &str) -> FenError {
+    match Board::from_fen(fen) {
+        Ok(_) => panic!("{} was accepted", fen),
+        Err(e) => e,
+    }
+}
+
+#[test]
+fn parses_the_starting_position() {
+    let board = Board::from_fen(STARTING_FEN).unwrap();
+    assert_eq!(board, Board::default());
+    assert_eq!(Board::default().to_fen(), STARTING_FEN);
+}
+
+#[test]
+fn parses_every_field() {
+    let board =
+        Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w Kq d6 3 7").unwrap();
+    assert_eq!(board.turn_player, PieceColor::White);
+    let rights = board.castling_rights();
+    assert!(rights.white_kingside && !rights.white_queenside);
+    assert!(!rights.black_kingside && rights.black_queenside);
+    assert_eq!(board.en_passant_target(), parse_square("d6"));
+    assert_eq!(board.halfmove_clock(), 3);
+    assert_eq!(board.fullmove_number(), 7);
+}
+
+#[test]
+fn move_counters_are_optional() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+    assert_eq!(board.halfmove_clock(), 0);
+    assert_eq!(board.fullmove_number(), 1);
+    assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+}
+
+#[test]
+fn round_trips() {
+    for fen in [
+        STARTING_FEN,
+        "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 3",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "4k3/8/8/8/8/8/8/4K3 w - - 99 120",
+    ] {
+        assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+    }
+}
+
+#[test]
+fn round_trips_after_moves() {
+    let mut board = Board::default();
+    for uci in ["e2e4", "c7c5", "g1f3", "d7d6", "e1e2"] {
+        let mv = board.parse_uci_move(uci).unwrap();
+        board.make_move(mv);
+        let fen = board.to_fen();
+        let parsed = Board::from_fen(&fen).unwrap();
+        assert_eq!(parsed.to_fen(), fen);
+        assert_eq!(parsed.hash(), board.hash(), "after {}", uci);
+    }
+    assert_eq!(
+        board.to_fen(),
+        "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPPKPPP/RNBQ1B1R b kq - 1 3"
+    );
+}
+
+#[test]
+fn rejects_malformed_fields() {
+    assert_eq!(parse_error(""), FenError::MissingField("board"));
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w -"),
+        FenError::MissingField("en passant")
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra"),
+        FenError::TooManyFields
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::WrongRankCount(7)
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K4 w - - 0 1"),
+        FenError::WrongRankLength(1)
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4X2K w - - 0 1"),
+        FenError::InvalidPiece('X')
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+        FenError::InvalidSideToMove("x".to_string())
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+        FenError::InvalidCastling("K".to_string())
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w - z9 0 1"),
+        FenError::InvalidEnPassant("z9".to_string())
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+        FenError::InvalidHalfmoveClock("x".to_string())
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+        FenError::InvalidFullmoveNumber("0".to_string())
+    );
+}
+
+#[test]
+fn rejects_missing_or_extra_kings() {
+    assert_eq!(
+        parse_error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::KingCount {
+            color: PieceColor::Black,
+            count: 0
+        }
+    );
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+        FenError::KingCount {
+            color: PieceColor::White,
+            count: 2
+        }
+    );
+}
+
+#[test]
+fn rejects_pawns_on_the_back_ranks() {
+    assert_eq!(
+        parse_error("4k3/8/8/8/8/8/8/P3K3 w - - 0 1"),
+        FenError::PawnOnBackRank("a1".to_string())
+    );
+    assert_eq!(
+        parse_error("4k2p/8/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::PawnOnBackRank("h8".to_string())
+    );
+}
+
+#[test]
+fn rejects_the_side_not_to_move_in_check() {
+    assert_eq!(
+        parse_error("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1"),
+        FenError::OpponentInCheck
+    );
+    // the same position is fine with black to move
+    assert!(Board::from_fen("4k3/4R3/8/8/8/8/8/4K3 b - - 0 1").is_ok());
+}
+
+#[test]
+fn rejects_en_passant_without_a_double_push() {
+    // no black pawn in front of e6
+    assert_eq!(
+        parse_error("4k3/8/8/3PP3/8/8/8/4K3 w - e6 0 1"),
+        FenError::EnPassantWithoutPawn("e6".to_string())
+    );
+    // the pawn could not have come from e7
+    assert_eq!(
+        parse_error("4k3/4p3/8/3Pp3/8/8/8/4K3 w - e6 0 1"),
+        FenError::EnPassantWithoutPawn("e6".to_string())
+    );
+    // a piece stands on the target
+    assert_eq!(
+        parse_error("4k3/8/4n3/3Pp3/8/8/8/4K3 w - e6 0 1"),
+        FenError::EnPassantWithoutPawn("e6".to_string())
+    );
+    // the target is on the wrong side of the board for the side to move
+    assert_eq!(
+        parse_error("4k3/8/8/3Pp3/8/8/8/4K3 w - e3 0 1"),
+        FenError::InvalidEnPassant("e3".to_string())
+    );
+    assert!(Board::from_fen("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1").is_ok());
+}
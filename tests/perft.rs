@@ -0,0 +1,103 @@
+//! Move generation checked against the published perft numbers from
+//! https://www.chessprogramming.org/Perft_Results. The deeper counts take long in
+//! a debug build, run them with `cargo test --release -- --ignored`.
+use xp_chess::board::fen::STARTING_FEN;
+use xp_chess::board::Board;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+/// checks perft for depths 1, 2, ... against `expected`, in that order
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let board = Board::from_fen(fen).unwrap();
+    for (depth, &nodes) in (1..).zip(expected) {
+        assert_eq!(board.perft(depth), nodes, "{} at depth {}", fen, depth);
+    }
+}
+fn assert_perft_at(fen: &str, depth: u32, nodes: u64) {
+    let board = Board::from_fen(fen).unwrap();
+    assert_eq!(board.perft(depth), nodes, "{} at depth {}", fen, depth);
+}
+
+#[test]
+fn start_position() {
+    assert_perft(STARTING_FEN, &[20, 400, 8902, 197_281]);
+}
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2039, 97_862]);
+}
+#[test]
+fn position_3() {
+    assert_perft(POSITION_3, &[14, 191, 2812, 43_238]);
+}
+#[test]
+fn position_4() {
+    assert_perft(POSITION_4, &[6, 264, 9467]);
+    assert_perft(POSITION_4_MIRRORED, &[6, 264, 9467]);
+}
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1486, 62_379]);
+}
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2079, 89_890]);
+}
+#[test]
+fn divide_adds_up_to_perft() {
+    let board = Board::from_fen(KIWIPETE).unwrap();
+    let divided = board.divide(3);
+    assert_eq!(divided.len(), 48);
+    assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97_862);
+    let castle = divided
+        .iter()
+        .find(|(mv, _)| mv.to_uci() == "e1g1")
+        .expect("white can castle kingside");
+    assert_eq!(castle.1, 2059);
+}
+#[test]
+fn perft_leaves_the_board_alone() {
+    let board = Board::from_fen(POSITION_5).unwrap();
+    let before = board.clone();
+    board.perft(2);
+    board.divide(2);
+    assert_eq!(board, before);
+}
+
+#[test]
+#[ignore = "slow in a debug build"]
+fn start_position_deep() {
+    assert_perft_at(STARTING_FEN, 5, 4_865_609);
+}
+#[test]
+#[ignore = "slow in a debug build"]
+fn kiwipete_deep() {
+    assert_perft_at(KIWIPETE, 4, 4_085_603);
+}
+#[test]
+#[ignore = "slow in a debug build"]
+fn position_3_deep() {
+    assert_perft_at(POSITION_3, 6, 11_030_083);
+}
+#[test]
+#[ignore = "slow in a debug build"]
+fn position_4_deep() {
+    assert_perft_at(POSITION_4, 5, 15_833_292);
+    assert_perft_at(POSITION_4_MIRRORED, 5, 15_833_292);
+}
+#[test]
+#[ignore = "slow in a debug build"]
+fn position_5_deep() {
+    assert_perft_at(POSITION_5, 4, 2_103_487);
+}
+#[test]
+#[ignore = "slow in a debug build"]
+fn position_6_deep() {
+    assert_perft_at(POSITION_6, 4, 3_894_594);
+}
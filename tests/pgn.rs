@@ -0,0 +1,194 @@
+use xp_chess::board::fen::FenError;
+use xp_chess::board::piece::PieceColor;
+use xp_chess::board::{parse_square, Board};
+use xp_chess::pgn::{parse_pgn, PgnError, PgnGame, PgnMove};
+
+const TWO_GAMES: &str = r#"[Event "Casual"]
+[Site "?"]
+[Date "2024.01.02"]
+[Round "1"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 1-0
+
+[Event "Second"]
+[Result "1/2-1/2"]
+
+1. d4 d5 2. c4 c6 1/2-1/2
+"#;
+
+fn sans(moves: &[PgnMove]) -> Vec<&str> {
+    moves.iter().map(|mv| mv.san.as_str()).collect()
+}
+fn parse_one(text: &str) -> PgnGame {
+    let mut games = parse_pgn(text).unwrap();
+    assert_eq!(games.len(), 1);
+    games.remove(0)
+}
+
+#[test]
+fn reads_every_game_in_a_file() {
+    let games = parse_pgn(TWO_GAMES).unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].tag("White"), Some("Anderssen"));
+    assert_eq!(games[0].result, "1-0");
+    assert_eq!(
+        sans(&games[0].moves),
+        ["e4", "e5", "f4", "exf4", "Bc4", "Qh4+", "Kf1"]
+    );
+    assert_eq!(games[1].tag("Event"), Some("Second"));
+    assert_eq!(games[1].result, "1/2-1/2");
+    assert_eq!(sans(&games[1].moves), ["d4", "d5", "c4", "c6"]);
+}
+
+#[test]
+fn games_without_a_result_end_at_the_next_tags() {
+    let games = parse_pgn("[Result \"0-1\"]\n1. f3 e5\n[Result \"*\"]\n1. e4\n").unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0].result, "0-1");
+    assert_eq!(sans(&games[1].moves), ["e4"]);
+}
+
+#[test]
+fn reads_nested_variations() {
+    let game =
+        parse_one("1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... e5 (1... c5 2. Nf3) 2. Nf3 *");
+    assert_eq!(sans(&game.moves), ["e4", "e5", "Nf3"]);
+    let d4 = &game.moves[0].variations[0];
+    assert_eq!(sans(d4), ["d4", "d5", "c4"]);
+    assert_eq!(sans(&d4[1].variations[0]), ["Nf6", "c4"]);
+    assert_eq!(sans(&game.moves[1].variations[0]), ["c5", "Nf3"]);
+}
+
+#[test]
+fn reads_nags_and_move_suffixes() {
+    let game = parse_one("1. e4! e5 $2 2. Qh5?! Nc6 $14 3. Bc4 Nf6?? 4. Qxf7# 1-0");
+    let nags: Vec<&[u8]> = game.moves.iter().map(|mv| mv.nags.as_slice()).collect();
+    assert_eq!(nags, [&[1][..], &[2], &[6], &[14], &[], &[4], &[]]);
+    assert_eq!(game.moves[6].san, "Qxf7#");
+}
+
+#[test]
+fn reads_comments() {
+    let game = parse_one(
+        "{Before the game} 1. e4 {best by test}\n{and more} e5 ; rest of line\n2. Nf3 {multi\nline} *",
+    );
+    assert_eq!(
+        game.moves[0].comment_before.as_deref(),
+        Some("Before the game")
+    );
+    assert_eq!(
+        game.moves[0].comment.as_deref(),
+        Some("best by test and more")
+    );
+    assert_eq!(game.moves[1].comment.as_deref(), Some("rest of line"));
+    assert_eq!(game.moves[2].comment.as_deref(), Some("multi line"));
+}
+
+#[test]
+fn reports_illegal_moves() {
+    let error = parse_pgn("[Result \"*\"]\n\n1. e4 e5\n2. Ke3 *\n\n").unwrap_err();
+    assert_eq!(
+        error,
+        PgnError::IllegalMove {
+            game: 1,
+            line: 4,
+            move_number: 2,
+            color: PieceColor::White,
+            san: "Ke3".to_string(),
+        }
+    );
+    let error = parse_pgn(&format!("{}\n1. e4 (1. e5) *", TWO_GAMES)).unwrap_err();
+    assert!(matches!(
+        error,
+        PgnError::IllegalMove { game: 3, ref san, color: PieceColor::White, .. } if san == "e5"
+    ));
+    // an ambiguous move is just as unplayable
+    let error = parse_pgn("[FEN \"4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1\"]\n1. Nd2 *").unwrap_err();
+    assert!(matches!(error, PgnError::IllegalMove { .. }));
+}
+
+#[test]
+fn reports_syntax_errors() {
+    for text in [
+        "1. e4 {never closed",
+        "1. e4 (1. d4 *",
+        "1. e4 ) *",
+        "[Event \"x\"\n",
+    ] {
+        assert!(
+            matches!(parse_pgn(text), Err(PgnError::Syntax { .. })),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn round_trips() {
+    let text = "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+        [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n\
+        1. e4 $1 {good} (1. d4 d5 (1... Nf6) 2. c4) 1... e5 2. Nf3 Nc6 *\n\n";
+    let game = parse_one(text);
+    assert_eq!(game.to_pgn().unwrap(), text);
+    assert_eq!(parse_one(&game.to_pgn().unwrap()), game);
+}
+
+#[test]
+fn writes_a_game_from_a_position() {
+    let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+    let mut game = PgnGame::from_position(&board);
+    let square = |name| parse_square(name).unwrap();
+    assert!(game.push_move(square("e8"), square("d8"), None));
+    assert!(game.push_move(square("e2"), square("e4"), None));
+    assert!(!game.push_move(square("e4"), square("e6"), None));
+    game.set_result("*");
+    let pgn = game.to_pgn().unwrap();
+    assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]"));
+    assert!(pgn.ends_with("12... Kd8 13. e4 *\n\n"), "{}", pgn);
+    assert_eq!(parse_one(&pgn).moves, game.moves);
+}
+
+#[test]
+fn writes_braces_in_comments_safely() {
+    let mut game = parse_one("1. e4 *");
+    game.moves[0].comment = Some("a } inside".to_string());
+    let pgn = game.to_pgn().unwrap();
+    assert!(pgn.contains("{a ) inside}"), "{}", pgn);
+    assert_eq!(
+        parse_one(&pgn).moves[0].comment.as_deref(),
+        Some("a ) inside")
+    );
+}
+
+#[test]
+fn refuses_to_write_with_a_bad_fen() {
+    let mut game = PgnGame::default();
+    game.set_tag("FEN", "8/8 w - -");
+    assert!(matches!(game.to_pgn(), Err(FenError::WrongRankCount(2))));
+    let error = parse_pgn("[FEN \"8/8 w - -\"]\n*").unwrap_err();
+    assert!(matches!(error, PgnError::InvalidFen { game: 1, .. }));
+}
+
+#[test]
+fn a_hand_edited_illegal_move_is_an_error() {
+    let mut game = parse_one("1. e4 e5 2. Nf3 *");
+    assert_eq!(game.final_board().unwrap().fullmove_number(), 2);
+    // swap white's second move for one that is not legal there
+    game.moves[2] = parse_one("1. e4 e5 2. Ke2 *").moves[2].clone();
+    game.moves[2].to = parse_square("e3").unwrap();
+    assert_eq!(
+        game.final_board(),
+        Err(PgnError::UnplayableMove {
+            ply: 3,
+            san: "Ke2".to_string()
+        })
+    );
+    assert!(!game.push_move(
+        parse_square("b8").unwrap(),
+        parse_square("c6").unwrap(),
+        None
+    ));
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::{board, play};
+use xp_chess::board::san::SanError;
+use xp_chess::board::Board;
+
+/// the SAN of a move given in UCI notation, checking that it reads back as the same move
+fn san(board: &mut Board, uci: &str) -> String {
+    let mv = board.parse_uci_move(uci).unwrap();
+    let before = board.clone();
+    let san = board.move_to_san(mv.from, mv.to, mv.promotion);
+    assert_eq!(*board, before, "writing {} changed the board", san);
+    assert_eq!(board.parse_san(&san), Ok(mv), "reading back {}", san);
+    san
+}
+
+#[test]
+fn pawn_and_piece_moves() {
+    let mut b = Board::default();
+    assert_eq!(san(&mut b, "e2e4"), "e4");
+    assert_eq!(san(&mut b, "g1f3"), "Nf3");
+    play(&mut b, &["e2e4", "d7d5"]);
+    assert_eq!(san(&mut b, "e4d5"), "exd5");
+    assert_eq!(san(&mut b, "f1b5"), "Bb5+");
+}
+
+#[test]
+fn disambiguates_by_file() {
+    let mut b = board("rn2k3/8/5n2/8/8/8/8/4K3 b - - 0 1");
+    assert_eq!(san(&mut b, "b8d7"), "Nbd7");
+    assert_eq!(san(&mut b, "f6d7"), "Nfd7");
+    // only the knight that can get there is named plainly
+    assert_eq!(san(&mut b, "f6e4"), "Ne4");
+    assert_eq!(
+        b.parse_san("Nd7"),
+        Err(SanError::Ambiguous("Nd7".to_string()))
+    );
+}
+
+#[test]
+fn disambiguates_by_rank() {
+    let mut b = board("7k/8/8/8/8/4R3/8/K3R3 w - - 0 1");
+    assert_eq!(san(&mut b, "e1e2"), "R1e2");
+    assert_eq!(san(&mut b, "e3e2"), "R3e2");
+    assert_eq!(san(&mut b, "e1d1"), "Rd1");
+}
+
+#[test]
+fn disambiguates_by_square() {
+    let mut b = board("8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1");
+    assert_eq!(san(&mut b, "h4e1"), "Qh4e1");
+    assert_eq!(san(&mut b, "e4e1"), "Qee1");
+    assert_eq!(san(&mut b, "h1e1"), "Q1e1");
+}
+
+#[test]
+fn en_passant() {
+    let mut b = Board::default();
+    play(&mut b, &["e2e4", "a7a6", "e4e5", "d7d5"]);
+    assert_eq!(san(&mut b, "e5d6"), "exd6");
+}
+
+#[test]
+fn promotion() {
+    let mut b = board("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(san(&mut b, "b7b8q"), "b8=Q+");
+    assert_eq!(san(&mut b, "b7b8n"), "b8=N");
+    assert_eq!(san(&mut b, "b7b8r"), "b8=R+");
+    let mut b = board("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+    assert_eq!(san(&mut b, "a7b8n"), "axb8=N");
+    assert_eq!(san(&mut b, "a7b8q"), "axb8=Q+");
+    // the '=' may be left out when reading
+    let mv = b.parse_san("axb8N").unwrap();
+    assert_eq!(mv.to_uci(), "a7b8n");
+    assert!(matches!(b.parse_san("axb8"), Err(SanError::Illegal(_))));
+}
+
+#[test]
+fn castling() {
+    let mut b = board("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert_eq!(san(&mut b, "e1g1"), "O-O");
+    assert_eq!(san(&mut b, "e1c1"), "O-O-O");
+    assert_eq!(b.parse_san("0-0-0").unwrap().to_uci(), "e1c1");
+    play(&mut b, &["a1b1"]);
+    assert_eq!(san(&mut b, "e8g8"), "O-O");
+    // castling into check is written with the suffix
+    let mut b = board("5k2/8/8/8/8/8/8/R3K3 w Q - 0 1");
+    assert_eq!(san(&mut b, "e1c1"), "O-O-O");
+    let mut b = board("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1");
+    assert_eq!(san(&mut b, "e1c1"), "O-O-O+");
+}
+
+#[test]
+fn check_and_mate_suffixes() {
+    let mut b = Board::default();
+    play(&mut b, &["f2f3", "e7e5", "g2g4"]);
+    assert_eq!(san(&mut b, "d8h4"), "Qh4#");
+    let mut b = Board::default();
+    play(&mut b, &["e2e4", "d7d6"]);
+    assert_eq!(san(&mut b, "f1b5"), "Bb5+");
+    // suffixes and annotations are ignored when reading
+    assert_eq!(b.parse_san("Bb5").unwrap().to_uci(), "f1b5");
+    assert_eq!(b.parse_san("Bb5+!?").unwrap().to_uci(), "f1b5");
+}
+
+#[test]
+fn rejects_what_is_not_a_move() {
+    let b = Board::default();
+    assert_eq!(
+        b.parse_san("Ke2"),
+        Err(SanError::Illegal("Ke2".to_string()))
+    );
+    assert_eq!(b.parse_san("e5"), Err(SanError::Illegal("e5".to_string())));
+    assert_eq!(b.parse_san("xyz"), Err(SanError::Syntax("xyz".to_string())));
+    assert_eq!(
+        b.parse_san("e8=X"),
+        Err(SanError::Syntax("e8=X".to_string()))
+    );
+    assert_eq!(
+        b.parse_san("O-O"),
+        Err(SanError::Illegal("O-O".to_string()))
+    );
+}
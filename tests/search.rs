@@ -0,0 +1,63 @@
+//! The engine search, on positions small enough to finish quickly.
+mod common;
+
+use common::board;
+use std::sync::atomic::AtomicBool;
+use xp_chess::board::Board;
+use xp_chess::engine::{search, Score, SearchLimits};
+
+#[test]
+fn finds_mate_in_one() {
+    let b = board("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1");
+    let result = search(&b, &SearchLimits::depth(4), &AtomicBool::new(false), |_| {});
+    assert_eq!(result.best_move.unwrap().to_uci(), "d1d8");
+    assert_eq!(result.score, Score::Mate(1));
+}
+
+#[test]
+fn finds_mate_in_two() {
+    let mut b = board("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1");
+    let result = search(&b, &SearchLimits::depth(5), &AtomicBool::new(false), |_| {});
+    assert_eq!(result.score, Score::Mate(2));
+    // the line it expects really does mate, whatever black plays in between
+    assert_eq!(result.pv.len(), 3);
+    for mv in result.pv {
+        b.make_move(mv);
+    }
+    assert!(b.is_checkmate());
+}
+
+#[test]
+fn has_no_move_when_mated() {
+    let b = board("3R2k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1");
+    let result = search(&b, &SearchLimits::depth(2), &AtomicBool::new(false), |_| {});
+    assert_eq!(result.best_move, None);
+}
+
+#[test]
+fn stops_when_told() {
+    let b = Board::default();
+    let stop = AtomicBool::new(true);
+    let mut infos = 0;
+    let result = search(&b, &SearchLimits::default(), &stop, |_| infos += 1);
+    // nothing finished, but there is still a move to play
+    assert_eq!(infos, 0);
+    assert_eq!(result.depth, 0);
+    assert!(b.legal_moves().contains(&result.best_move.unwrap()));
+}
+
+#[test]
+fn keeps_to_the_depth_limit() {
+    let b = Board::default();
+    let mut depths = vec![];
+    let result = search(
+        &b,
+        &SearchLimits::depth(3),
+        &AtomicBool::new(false),
+        |info| depths.push(info.depth),
+    );
+    assert_eq!(depths, [1, 2, 3]);
+    assert_eq!(result.depth, 3);
+    assert!(matches!(result.score, Score::Centipawns(_)));
+    assert_eq!(result.best_move.as_ref(), result.pv.first());
+}
@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use xp_chess::board::Board;
+use xp_chess::engine::Score;
+use xp_chess::uci::{EngineConfig, GoCommand, UciEngine, UciError, UciInfo, UciMessage};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn fake_engine() -> EngineConfig {
+    EngineConfig {
+        name: "fake".to_string(),
+        path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fake-uci-engine.sh"),
+        args: vec![],
+        options: vec![("Skill".to_string(), "3".to_string())],
+    }
+}
+/// reads messages until one matches, failing the test if none comes
+fn expect<F: Fn(&UciMessage) -> bool>(engine: &UciEngine, matches: F) -> UciMessage {
+    loop {
+        match engine.recv_timeout(TIMEOUT) {
+            Ok(Some(message)) if matches(&message) => return message,
+            Ok(Some(_)) => (),
+            other => panic!("no matching message, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn handshake_reports_the_engine_name() {
+    let engine = UciEngine::start(&fake_engine(), TIMEOUT).unwrap();
+    assert_eq!(engine.name(), "Fake Engine");
+}
+#[test]
+fn position_is_sent_as_start_fen_and_moves() {
+    let mut engine = UciEngine::start(&fake_engine(), TIMEOUT).unwrap();
+    let mut board = Board::default();
+    for uci in ["e2e4", "e7e5", "g1f3"] {
+        let mv = board.parse_uci_move(uci).unwrap();
+        board.make_move(mv);
+    }
+    engine.set_position(&board).unwrap();
+    let UciMessage::Info(info) = expect(&engine, |m| matches!(m, UciMessage::Info(_))) else {
+        unreachable!()
+    };
+    assert_eq!(
+        info.string.as_deref(),
+        Some(
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 \
+             moves e2e4 e7e5 g1f3"
+        )
+    );
+}
+#[test]
+fn go_reports_info_and_bestmove() {
+    let mut engine = UciEngine::start(&fake_engine(), TIMEOUT).unwrap();
+    engine.set_position(&Board::default()).unwrap();
+    engine
+        .go(&GoCommand {
+            move_time: Some(Duration::from_millis(100)),
+            ..GoCommand::default()
+        })
+        .unwrap();
+    let message = expect(
+        &engine,
+        |m| matches!(m, UciMessage::Info(info) if info.depth.is_some()),
+    );
+    assert_eq!(
+        message,
+        UciMessage::Info(UciInfo {
+            depth: Some(3),
+            seldepth: Some(5),
+            score: Some(Score::Centipawns(25)),
+            nodes: Some(1234),
+            nps: Some(1000),
+            time: Some(Duration::from_millis(12)),
+            pv: vec!["e2e4".to_string(), "e7e5".to_string()],
+            ..UciInfo::default()
+        })
+    );
+    let UciMessage::Info(info) = expect(&engine, |m| matches!(m, UciMessage::Info(_))) else {
+        unreachable!()
+    };
+    assert_eq!(info.score, Some(Score::Mate(-3)));
+    assert_eq!(info.nodes, Some(2000));
+    assert_eq!(
+        expect(&engine, |m| matches!(m, UciMessage::BestMove { .. })),
+        UciMessage::BestMove {
+            best_move: "e2e4".to_string(),
+            ponder: Some("e7e5".to_string()),
+        }
+    );
+}
+#[test]
+fn infinite_search_answers_stop() {
+    let mut engine = UciEngine::start(&fake_engine(), TIMEOUT).unwrap();
+    engine
+        .go(&GoCommand {
+            infinite: true,
+            ..GoCommand::default()
+        })
+        .unwrap();
+    expect(&engine, |m| matches!(m, UciMessage::Info(_)));
+    assert_eq!(
+        engine.recv_timeout(Duration::from_millis(100)).unwrap(),
+        None
+    );
+    engine.stop().unwrap();
+    assert_eq!(
+        expect(&engine, |m| matches!(m, UciMessage::BestMove { .. })),
+        UciMessage::BestMove {
+            best_move: "d2d4".to_string(),
+            ponder: None,
+        }
+    );
+}
+#[test]
+fn missing_engine_fails_to_start() {
+    let config = EngineConfig {
+        path: PathBuf::from("/nonexistent/engine"),
+        ..fake_engine()
+    };
+    assert!(matches!(
+        UciEngine::start(&config, TIMEOUT),
+        Err(UciError::Io(_))
+    ));
+}
+#[test]
+fn go_command_round_trips() {
+    let line = "go wtime 60000 btime 59000 winc 1000 binc 1000 movestogo 20";
+    let go = GoCommand::parse(line.split_whitespace().skip(1).collect());
+    assert_eq!(go.white_time, Some(Duration::from_secs(60)));
+    assert_eq!(go.moves_to_go, Some(20));
+    assert_eq!(go.to_string(), line);
+}
@@ -0,0 +1,142 @@
+//! The built-in engine's UCI binary, driven through the same client the GUI uses.
+use std::path::PathBuf;
+use std::time::Duration;
+
+use xp_chess::board::Board;
+use xp_chess::uci::{EngineConfig, UciEngine, UciMessage};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn start(options: &[(&str, &str)]) -> UciEngine {
+    let config = EngineConfig {
+        name: "xp-chess".to_string(),
+        path: PathBuf::from(env!("CARGO_BIN_EXE_xp-chess-uci")),
+        args: vec![],
+        options: options
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+    };
+    UciEngine::start(&config, TIMEOUT).unwrap()
+}
+/// reads messages until the bestmove, returning it with the depths of the info lines before it
+fn best_move(engine: &UciEngine) -> (String, Vec<u32>) {
+    let mut depths = vec![];
+    loop {
+        match engine.recv_timeout(TIMEOUT) {
+            Ok(Some(UciMessage::BestMove { best_move, .. })) => return (best_move, depths),
+            Ok(Some(UciMessage::Info(info))) => depths.extend(info.depth),
+            Ok(Some(_)) => (),
+            other => panic!("no bestmove, got {:?}", other),
+        }
+    }
+}
+/// the text of the `info string` the engine answers the last command with, if any
+fn info_string(engine: &mut UciEngine) -> Option<String> {
+    engine.send("isready").unwrap();
+    let mut string = None;
+    loop {
+        match engine.recv_timeout(TIMEOUT) {
+            Ok(Some(UciMessage::Info(info))) if info.string.is_some() => string = info.string,
+            Ok(Some(UciMessage::ReadyOk)) => return string,
+            Ok(Some(_)) => (),
+            other => panic!("no readyok, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn handshake_reports_the_engine_name() {
+    let engine = start(&[]);
+    assert_eq!(engine.name(), "xp-chess");
+}
+
+#[test]
+fn plays_from_startpos_moves() {
+    let mut engine = start(&[]);
+    engine
+        .send("position startpos moves f2f3 e7e5 g2g4")
+        .unwrap();
+    engine.send("go depth 3").unwrap();
+    assert_eq!(best_move(&engine).0, "d8h4");
+    // an illegal move leaves the position as it was
+    engine.send("position startpos moves e2e5").unwrap();
+    assert_eq!(
+        info_string(&mut engine).as_deref(),
+        Some("illegal move e2e5")
+    );
+    engine.send("go depth 3").unwrap();
+    assert_eq!(best_move(&engine).0, "d8h4");
+}
+
+#[test]
+fn plays_from_a_fen() {
+    let mut engine = start(&[]);
+    let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+    engine.set_position(&board).unwrap();
+    engine.send("go depth 3").unwrap();
+    assert_eq!(best_move(&engine).0, "d1d8");
+    // a mated side has nothing to play
+    engine
+        .send("position fen 3R2k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1")
+        .unwrap();
+    engine.send("go depth 3").unwrap();
+    assert_eq!(best_move(&engine).0, "0000");
+}
+
+#[test]
+fn go_depth_searches_that_deep() {
+    let mut engine = start(&[]);
+    engine.send("position startpos").unwrap();
+    engine.send("go depth 3").unwrap();
+    let (best_move, depths) = best_move(&engine);
+    assert_eq!(depths, [1, 2, 3]);
+    assert!(Board::default().parse_uci_move(&best_move).is_some());
+}
+
+#[test]
+fn answers_without_its_own_clock() {
+    let mut engine = start(&[]);
+    // black to move, but only white's time is given
+    engine.send("position startpos moves e2e4").unwrap();
+    engine.send("go wtime 60000 winc 1000").unwrap();
+    let best_move = best_move(&engine).0;
+    let mut board = Board::default();
+    board.make_move(board.parse_uci_move("e2e4").unwrap());
+    assert!(board.parse_uci_move(&best_move).is_some());
+}
+
+#[test]
+fn stop_ends_an_infinite_search() {
+    let mut engine = start(&[]);
+    engine.send("position startpos").unwrap();
+    engine.send("go infinite").unwrap();
+    while let Some(message) = engine.recv_timeout(Duration::from_millis(200)).unwrap() {
+        assert!(
+            !matches!(message, UciMessage::BestMove { .. }),
+            "bestmove before stop"
+        );
+    }
+    engine.stop().unwrap();
+    let best_move = best_move(&engine).0;
+    assert!(Board::default().parse_uci_move(&best_move).is_some());
+}
+
+#[test]
+fn setoption_checks_names_and_values() {
+    // the client sets the configured options during the handshake
+    let mut engine = start(&[("Move Overhead", "50")]);
+    assert_eq!(info_string(&mut engine), None);
+    engine
+        .send("setoption name Move Overhead value soon")
+        .unwrap();
+    assert_eq!(
+        info_string(&mut engine).as_deref(),
+        Some("invalid Move Overhead soon")
+    );
+    engine.send("setoption name Hash value 64").unwrap();
+    assert_eq!(
+        info_string(&mut engine).as_deref(),
+        Some("unknown option Hash")
+    );
+}
@@ -0,0 +1,82 @@
+//! Hashes checked against the example keys of the Polyglot book format, see
+//! http://hgm.nubati.net/book_format.html
+mod common;
+
+use common::{board, play};
+use xp_chess::board::Board;
+
+fn assert_keys(moves: &[&str], key: u64) {
+    let mut board = Board::default();
+    play(&mut board, moves);
+    assert_eq!(board.polyglot_key(), key, "after {:?}", moves);
+    assert_eq!(board.hash(), key, "incremental hash after {:?}", moves);
+}
+
+#[test]
+fn matches_polyglot_reference_keys() {
+    assert_keys(&[], 0x463b96181691fc9c);
+    assert_keys(&["e2e4"], 0x823c9b50fd114196);
+    assert_keys(&["e2e4", "d7d5"], 0x0756b94461c50fb0);
+    assert_keys(&["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4);
+    // black could take en passant here, so the file counts
+    assert_keys(&["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78);
+    assert_keys(
+        &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"],
+        0x652a607ca3f242c1,
+    );
+    assert_keys(
+        &["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"],
+        0x00fdd303c946bdd9,
+    );
+    assert_keys(
+        &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"],
+        0x3c8123ea7b067637,
+    );
+    assert_keys(
+        &["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"],
+        0x5c3f9b829b279560,
+    );
+}
+#[test]
+fn fen_and_move_order_give_the_same_hash() {
+    let mut board = Board::default();
+    play(&mut board, &["g1f3", "g8f6", "b1c3", "b8c6"]);
+    let mut other = Board::default();
+    play(&mut other, &["b1c3", "b8c6", "g1f3", "g8f6"]);
+    let from_fen = Board::from_fen(&board.to_fen()).unwrap();
+    assert_eq!(board.hash(), other.hash());
+    assert_eq!(board.hash(), from_fen.hash());
+}
+#[test]
+fn incremental_hash_survives_special_moves_and_undo() {
+    // en passant, castling both ways and a promotion with capture, then rooks
+    // taking rooks, which ends castling on both wings
+    let games: [(&str, &[&str]); 2] = [
+        (
+            "r3k2r/1P4p1/8/3pP3/8/8/6P1/R3K2R w KQkq d6 0 1",
+            &["e5d6", "e8g8", "e1c1", "f8f1", "b7a8q", "g8f7"],
+        ),
+        (
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            &["a1a8", "e8e7", "h1h8"],
+        ),
+    ];
+    for (fen, moves) in games {
+        let mut board = board(fen);
+        let start = board.hash();
+        play(&mut board, moves);
+        assert_eq!(board.hash(), board.polyglot_key());
+        while board.undo_move().is_some() {
+            assert_eq!(board.hash(), board.polyglot_key());
+        }
+        assert_eq!(board.hash(), start);
+    }
+}
+#[test]
+fn side_to_move_and_castling_change_the_hash() {
+    let white = board("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    let black = board("4k3/8/8/8/8/8/8/R3K2R b KQ - 0 1");
+    let no_castling = board("4k3/8/8/8/8/8/8/R3K2R w - - 0 1");
+    assert_ne!(white.hash(), black.hash());
+    assert_ne!(white.hash(), no_castling.hash());
+}